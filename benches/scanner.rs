@@ -0,0 +1,118 @@
+//! Scanning throughput over synthetic Lox programs of a few megabytes each.
+//! Run with `cargo bench`; criterion reports the throughput of every benchmark in MiB/s.
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use rlox_lexer::scanner::Scanner;
+use rlox_lexer::LexerConfig;
+
+/// Approximate size of every generated program
+const PROGRAM_SIZE: usize = 4 * 1024 * 1024;
+
+/// Typical Lox code: declarations, control flow, strings and numbers, mostly ASCII
+const CODE: &str = r#"// Linked list of points
+class Point {
+  init(x, y) {
+    this.x = x;
+    this.y = y;
+  }
+
+  distance(other) {
+    var dx = this.x - other.x;
+    var dy = this.y - other.y;
+    return dx * dx + dy * dy >= 0.25e-3;
+  }
+}
+
+fun fibonacci(n) {
+  if (n <= 1) return n;
+  return fibonacci(n - 2) + fibonacci(n - 1);
+}
+
+var total = 0;
+for (var i = 0; i < 1_000; i = i + 1) {
+  total = total + fibonacci(i) * 3.14159 / 0x2A;
+  if (total != nil and !false) print "total: " + total;
+}
+"#;
+
+/// Code that is mostly comments and whitespace
+const COMMENTS: &str = r#"/*
+ * The scanner skips comments without producing tokens, so this measures how fast it moves
+ * over text it does not care about.
+ */
+//      An indented line comment followed by a blank line
+
+/// A doc comment
+fun identity(value) { return value; } // trailing comment
+"#;
+
+/// Code with non-ASCII strings, comments and identifiers
+const UNICODE: &str = r#"// Grüße aus Köln — 東京からこんにちは
+var größe = 42;
+var 名前 = "データ: λx → x² ∈ ℝ";
+fun Δ(α, β) { return α - β; }
+print "¡Hola, señor! 🦀 " + Δ(größe, 3.5);
+"#;
+
+/// Repeats the snippet until the program reaches `PROGRAM_SIZE`
+fn program(snippet: &str) -> String {
+    snippet.repeat(PROGRAM_SIZE / snippet.len() + 1)
+}
+
+fn scan(c: &mut Criterion) {
+    let programs = [
+        ("code", program(CODE), LexerConfig::new()),
+        ("comments", program(COMMENTS), LexerConfig::new()),
+        (
+            "unicode",
+            program(UNICODE),
+            LexerConfig::new().unicode_identifiers(true),
+        ),
+    ];
+
+    let mut group = c.benchmark_group("scan");
+    group.sample_size(20);
+    for (name, code, config) in &programs {
+        group.throughput(Throughput::Bytes(code.len() as u64));
+        group.bench_with_input(BenchmarkId::new("tokens", name), code, |b, code| {
+            b.iter(|| {
+                Scanner::with_config(code, config.clone())
+                    .filter_map(Result::ok)
+                    .count()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("token_refs", name), code, |b, code| {
+            b.iter(|| {
+                let mut scanner = Scanner::with_config(code, config.clone());
+                scanner.token_refs().filter_map(Result::ok).count()
+            })
+        });
+    }
+    group.finish();
+
+    let code = program(CODE);
+    let mut group = c.benchmark_group("config");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(code.len() as u64));
+    let configs = [
+        ("lossless", LexerConfig::new().lossless(true)),
+        ("comments", LexerConfig::new().comments(true)),
+        ("exact_numbers", LexerConfig::new().exact_numbers(true)),
+    ];
+    for (name, config) in configs {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                Scanner::with_config(black_box(&code), config.clone())
+                    .filter_map(Result::ok)
+                    .count()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, scan);
+criterion_main!(benches);
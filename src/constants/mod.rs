@@ -3,6 +3,7 @@ use phf::phf_map;
 
 pub const NEWLINE: char = '\n';
 
+/// Keywords of standard Lox, as specified in Crafting Interpreters.
 /// https://crates.io/crates/phf
 pub static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "and"       => TokenType::And,
@@ -13,7 +14,8 @@ pub static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "fun"       => TokenType::Fun,
     "if"        => TokenType::If,
     "nil"       => TokenType::Nil,
-    "or"        => TokenType::Print,
+    "or"        => TokenType::Or,
+    "print"     => TokenType::Print,
     "return"    => TokenType::Return,
     "super"     => TokenType::Super,
     "this"      => TokenType::This,
@@ -21,3 +23,47 @@ pub static KEYWORDS: phf::Map<&'static str, TokenType> = phf_map! {
     "var"       => TokenType::Var,
     "while"     => TokenType::While,
 };
+
+/// Token types by their name, as printed by `Debug`.
+/// Used to read token types back from serialized token streams.
+pub static TOKEN_TYPES: phf::Map<&'static str, TokenType> = phf_map! {
+    "LeftParen"     => TokenType::LeftParen,
+    "RightParen"    => TokenType::RightParen,
+    "LeftBrace"     => TokenType::LeftBrace,
+    "RightBrace"    => TokenType::RightBrace,
+    "Comma"         => TokenType::Comma,
+    "Dot"           => TokenType::Dot,
+    "Minus"         => TokenType::Minus,
+    "Plus"          => TokenType::Plus,
+    "SemiColon"     => TokenType::SemiColon,
+    "Slash"         => TokenType::Slash,
+    "Star"          => TokenType::Star,
+    "Bang"          => TokenType::Bang,
+    "BangEqual"     => TokenType::BangEqual,
+    "Equal"         => TokenType::Equal,
+    "EqualEqual"    => TokenType::EqualEqual,
+    "Greater"       => TokenType::Greater,
+    "GreaterEqual"  => TokenType::GreaterEqual,
+    "Less"          => TokenType::Less,
+    "LessEqual"     => TokenType::LessEqual,
+    "Identifier"    => TokenType::Identifier,
+    "String"        => TokenType::String,
+    "Number"        => TokenType::Number,
+    "And"           => TokenType::And,
+    "Class"         => TokenType::Class,
+    "Else"          => TokenType::Else,
+    "False"         => TokenType::False,
+    "Fun"           => TokenType::Fun,
+    "For"           => TokenType::For,
+    "If"            => TokenType::If,
+    "Nil"           => TokenType::Nil,
+    "Or"            => TokenType::Or,
+    "Print"         => TokenType::Print,
+    "Return"        => TokenType::Return,
+    "Super"         => TokenType::Super,
+    "This"          => TokenType::This,
+    "True"          => TokenType::True,
+    "Var"           => TokenType::Var,
+    "While"         => TokenType::While,
+    "Eof"           => TokenType::Eof,
+};
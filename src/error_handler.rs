@@ -1,30 +1,447 @@
+use std::env;
 use std::fmt;
+use std::io::{stderr, IsTerminal};
 
-#[warn(dead_code)]
-pub(crate) fn error(line: u16, message: String) {
-    report(line, message);
+use crate::scanner::Span;
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+pub(crate) fn error(source: &str, file_name: &str, diagnostic: &Diagnostic) {
+    report(source, file_name, diagnostic);
+}
+
+/// Prints the diagnostic to stderr, in colour only if stderr is a terminal.
+/// Setting `NO_COLOR` turns colours off regardless.
+pub fn report(source: &str, file_name: &str, diagnostic: &Diagnostic) {
+    let colored = stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+    eprint!(
+        "{}",
+        Renderer::new(source, file_name)
+            .colored(colored)
+            .render(diagnostic)
+    );
 }
 
-#[warn(dead_code)]
-fn report(line: u16, message: String) {
-    println!("Error in line [[{}]]: caused by: [[{}]]", line, message);
+/// How serious a diagnostic is.
+/// Errors mean the code string is not valid Lox; warnings point at valid but suspicious code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
 }
 
-// Define our error types. These may be customized for our error handling cases.
-// Now we will be able to write our own errors, defer to an underlying error
-// implementation, or do something in between.
-#[derive(Debug, Clone)]
-pub struct ParserError {
-    pub(crate) _msg: String,
+/// Name of the number literals written in the given radix
+fn radix_name(radix: u32) -> &'static str {
+    match radix {
+        16 => "hexadecimal",
+        8 => "octal",
+        2 => "binary",
+        _ => "decimal",
+    }
+}
+
+// Define our error types. Each variant carries the span of the offending source so that
+// the error can be pointed out precisely, and whatever extra info is needed to explain it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LexError {
+    /// A string literal without a closing quote.
+    /// The span runs from the opening quote to the end of the line it starts on.
+    UnterminatedString { span: Span },
+    /// A character that does not start any token
+    UnexpectedCharacter { character: char, span: Span },
+    /// A number literal that could not be converted into a value
+    MalformedNumber { lexeme: String, span: Span },
+    /// A number literal ending in an exponent marker (and sign) without any exponent digits
+    MissingExponentDigits { lexeme: String, span: Span },
+    /// A digit not allowed in the radix of a `0x`, `0o` or `0b` literal. The span covers the digit.
+    InvalidDigit { digit: char, radix: u32, span: Span },
+    /// A `0x`, `0o` or `0b` prefix without any digits
+    MissingDigits { lexeme: String, span: Span },
+    /// A number literal too large for a float, or a `0x`, `0o` or `0b` literal too large for
+    /// 64 bits
+    NumberOverflow { lexeme: String, span: Span },
+    /// A backslash followed by a character that does not form an escape sequence
+    UnknownEscape { character: char, span: Span },
+    /// A backslash at the end of a line or of the code string
+    IncompleteEscape { span: Span },
+    /// A block comment without a closing `*/`. The span covers the opening `/*`.
+    UnterminatedBlockComment { span: Span },
+    /// A `\u` escape that is not `\u{` 1 to 6 hex digits `}` naming a valid code point
+    InvalidUnicodeEscape { sequence: String, span: Span },
+}
+
+impl LexError {
+    /// Region of the code string the error refers to
+    pub fn span(&self) -> Span {
+        match self {
+            LexError::UnterminatedString { span }
+            | LexError::UnexpectedCharacter { span, .. }
+            | LexError::MalformedNumber { span, .. }
+            | LexError::MissingExponentDigits { span, .. }
+            | LexError::InvalidDigit { span, .. }
+            | LexError::MissingDigits { span, .. }
+            | LexError::NumberOverflow { span, .. }
+            | LexError::UnknownEscape { span, .. }
+            | LexError::IncompleteEscape { span }
+            | LexError::UnterminatedBlockComment { span }
+            | LexError::InvalidUnicodeEscape { span, .. } => *span,
+        }
+    }
+
+    /// Human readable description of the error
+    pub fn message(&self) -> String {
+        match self {
+            LexError::UnterminatedString { .. } => String::from("unterminated string"),
+            LexError::UnexpectedCharacter { character, .. } => {
+                format!("unexpected character {:?}", character)
+            }
+            LexError::MalformedNumber { lexeme, .. } => {
+                format!("malformed number literal {:?}", lexeme)
+            }
+            LexError::MissingExponentDigits { lexeme, .. } => {
+                format!("missing exponent digits in number literal {:?}", lexeme)
+            }
+            LexError::InvalidDigit { digit, radix, .. } => {
+                format!(
+                    "invalid digit {:?} in {} literal",
+                    digit,
+                    radix_name(*radix)
+                )
+            }
+            LexError::MissingDigits { lexeme, .. } => {
+                format!("missing digits in number literal {:?}", lexeme)
+            }
+            LexError::NumberOverflow { lexeme, .. } => {
+                format!("number literal {:?} is too large", lexeme)
+            }
+            LexError::UnknownEscape { character, .. } => {
+                format!("unknown escape sequence `\\{}`", character)
+            }
+            LexError::IncompleteEscape { .. } => String::from("incomplete escape sequence"),
+            LexError::UnterminatedBlockComment { .. } => String::from("unterminated block comment"),
+            LexError::InvalidUnicodeEscape { sequence, .. } => {
+                format!("invalid unicode escape `{}`", sequence)
+            }
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        Severity::Error
+    }
+
+    /// Hint on how to fix the error, if there is an obvious one
+    pub fn help(&self) -> Option<String> {
+        match self {
+            LexError::UnterminatedString { .. } => {
+                Some(String::from("add a closing `\"` to end the string"))
+            }
+            LexError::UnexpectedCharacter { .. } => None,
+            LexError::MalformedNumber { .. } => None,
+            LexError::MissingExponentDigits { .. } => Some(String::from(
+                "an exponent is written as `e` or `E`, an optional sign and digits, as in `1.5e-3`",
+            )),
+            LexError::InvalidDigit { radix, .. } => Some(format!(
+                "{} literals only use the digits {}",
+                radix_name(*radix),
+                match radix {
+                    16 => "0-9 and a-f",
+                    8 => "0-7",
+                    _ => "0 and 1",
+                }
+            )),
+            LexError::MissingDigits { .. } => None,
+            LexError::NumberOverflow { .. } => Some(String::from(
+                "numbers must be below about 1.8e308, and hexadecimal, octal and binary \
+                 literals must fit in 64 bits",
+            )),
+            LexError::UnknownEscape { .. } => Some(String::from(
+                "supported escapes are `\\\"`, `\\\\`, `\\n`, `\\t`, `\\r`, `\\0` and `\\u{XXXX}`",
+            )),
+            LexError::UnterminatedBlockComment { .. } => {
+                Some(String::from("add a closing `*/` to end the comment"))
+            }
+            LexError::IncompleteEscape { .. } => Some(String::from(
+                "a backslash must be followed by an escape character on the same line",
+            )),
+            LexError::InvalidUnicodeEscape { .. } => Some(String::from(
+                "unicode escapes are written as `\\u{` followed by 1 to 6 hex digits and `}`",
+            )),
+        }
+    }
 }
 
 // Generation of an error is completely separate from how it is displayed.
-// There's no need to be concerned about cluttering complex logic with the display style.
-//
-// Note that we don't store any extra info about the errors. This means we can't state
-// which string failed to parse without modifying our types to carry that information.
-impl fmt::Display for ParserError {
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let span = self.span();
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.message(),
+            span.start_line,
+            span.start_column
+        )
+    }
+}
+
+impl std::error::Error for LexError {}
+
+/// A message about the code string produced while scanning it.
+/// Diagnostics are collected by the scanner so that callers can inspect them after scanning.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub span: Span,
+    /// Extra hints shown below the source snippet
+    pub help: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: String, span: Span) -> Self {
+        Self {
+            severity: Severity::Error,
+            message,
+            span,
+            help: vec![],
+        }
+    }
+
+    pub fn warning(message: String, span: Span) -> Self {
+        Self {
+            severity: Severity::Warning,
+            message,
+            span,
+            help: vec![],
+        }
+    }
+
+    /// Adds a hint to the diagnostic
+    pub fn with_help(mut self, help: String) -> Self {
+        self.help.push(help);
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(error: &LexError) -> Self {
+        Self {
+            severity: error.severity(),
+            message: error.message(),
+            span: error.span(),
+            help: error.help().into_iter().collect(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "invalid first item to double")
+        write!(
+            f,
+            "{}: {} at line {}, column {}",
+            self.severity, self.message, self.span.start_line, self.span.start_column
+        )
+    }
+}
+
+/// Renders diagnostics rustc-style: a header, the location, the offending line of the code
+/// string with the span underlined, and any help notes.
+///
+/// ```text
+/// error: unterminated string
+///  --> program.lox:2:5
+///   |
+/// 2 | x = "oops
+///   |     ^^^^^
+///   |
+///   = help: add a closing `"` to end the string
+/// ```
+pub struct Renderer<'a> {
+    source: &'a str,
+    file_name: &'a str,
+    colored: bool,
+}
+
+impl<'a> Renderer<'a> {
+    /// Creates a renderer for diagnostics of `source`, which was read from `file_name`.
+    /// Colours are off by default.
+    pub fn new(source: &'a str, file_name: &'a str) -> Self {
+        Self {
+            source,
+            file_name,
+            colored: false,
+        }
+    }
+
+    /// Turns ANSI colours on or off
+    pub fn colored(mut self, colored: bool) -> Self {
+        self.colored = colored;
+        self
+    }
+
+    /// Wraps text in the given ANSI style if colours are on
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.colored {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            String::from(text)
+        }
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        let span = diagnostic.span;
+        let severity_style = match diagnostic.severity {
+            Severity::Warning => YELLOW,
+            Severity::Error => RED,
+        };
+        let line_number = span.start_line.to_string();
+        let padding = " ".repeat(line_number.len());
+        let gutter = self.paint(BLUE, &format!("{} |", padding));
+        let source_line = self
+            .source
+            .lines()
+            .nth(span.start_line.saturating_sub(1))
+            .unwrap_or("");
+
+        // Spans running over several lines are underlined until the end of their first line
+        let line_width = source_line.chars().count() + 1;
+        let end_column = if span.end_line == span.start_line {
+            span.end_column
+        } else {
+            line_width
+        };
+        let underline_width = end_column.saturating_sub(span.start_column).max(1);
+        // Keep tabs so that the underline stays aligned with the source line
+        let indent: String = source_line
+            .chars()
+            .take(span.start_column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+
+        let mut output = format!(
+            "{}{}\n",
+            self.paint(severity_style, &diagnostic.severity.to_string()),
+            self.paint(BOLD, &format!(": {}", diagnostic.message))
+        );
+        output += &format!(
+            "{}{} {}:{}:{}\n",
+            padding,
+            self.paint(BLUE, "-->"),
+            self.file_name,
+            span.start_line,
+            span.start_column
+        );
+        output += &format!("{}\n", gutter);
+        output += &format!(
+            "{} {}\n",
+            self.paint(BLUE, &format!("{} |", line_number)),
+            source_line
+        );
+        output += &format!(
+            "{} {}{}\n",
+            gutter,
+            indent,
+            self.paint(severity_style, &"^".repeat(underline_width))
+        );
+        if !diagnostic.help.is_empty() {
+            output += &format!("{}\n", gutter);
+            for help in &diagnostic.help {
+                output += &format!("{} {} help: {}\n", padding, self.paint(BLUE, "="), help);
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diagnostic_from_lex_error() {
+        let span = Span {
+            start: 4,
+            end: 5,
+            start_line: 2,
+            start_column: 3,
+            end_line: 2,
+            end_column: 4,
+        };
+        let error = LexError::UnexpectedCharacter {
+            character: '#',
+            span,
+        };
+        let diagnostic = Diagnostic::from(&error);
+        assert!(diagnostic.is_error());
+        assert_eq!(diagnostic.span, span);
+        assert_eq!(diagnostic.message, "unexpected character '#'");
+        assert_eq!(
+            error.to_string(),
+            "unexpected character '#' at line 2, column 3"
+        );
+    }
+
+    #[test]
+    fn test_render_plain() {
+        let source = "var x = 1;\nx = \"oops";
+        let error = LexError::UnterminatedString {
+            span: Span {
+                start: 15,
+                end: 20,
+                start_line: 2,
+                start_column: 5,
+                end_line: 2,
+                end_column: 10,
+            },
+        };
+        let rendered = Renderer::new(source, "program.lox").render(&Diagnostic::from(&error));
+        assert_eq!(
+            rendered,
+            "error: unterminated string\n \
+             --> program.lox:2:5\n  \
+             |\n\
+             2 | x = \"oops\n  \
+             |     ^^^^^\n  \
+             |\n  \
+             = help: add a closing `\"` to end the string\n"
+        );
+    }
+
+    #[test]
+    fn test_render_colored() {
+        let source = "\t#";
+        let diagnostic = Diagnostic::warning(
+            String::from("suspicious character"),
+            Span {
+                start: 1,
+                end: 2,
+                start_line: 1,
+                start_column: 2,
+                end_line: 1,
+                end_column: 3,
+            },
+        );
+        let rendered = Renderer::new(source, "<stdin>")
+            .colored(true)
+            .render(&diagnostic);
+        assert!(rendered.starts_with("\x1b[1;33mwarning\x1b[0m"));
+        assert!(rendered.contains("|\x1b[0m \t\x1b[1;33m^\x1b[0m\n"));
     }
 }
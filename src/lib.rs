@@ -1,18 +1,27 @@
-use std::fs;
 use std::io::stdin;
 
 use scanner::Scanner;
+pub use error_handler::{report, Diagnostic, LexError, Renderer, Severity};
 pub use rlox_lib::token::{Literal, LiteralType, Token, TokenType};
+pub use scanner::{
+    Comment, CommentKind, Dialect, DialectError, Item, LexerConfig, Number, Span, SpannedToken,
+    TokenRef, Trivia, TriviaKind,
+};
 pub mod constants;
 mod error_handler;
 pub mod scanner;
+pub mod serialization;
 
-/// Run the source code file
-pub fn run_file(file_path: &str) -> impl Iterator<Item = Token<LiteralType>> + '_ {
-    let content = fs::read_to_string(file_path).expect("Invalid file path");
-    run(&content)
-        .collect::<Vec<Token<LiteralType>>>()
-        .into_iter()
+/// Run the source code of a file, as read by the caller (e.g. with `fs::read_to_string`).
+/// The file is scanned lazily, as the returned iterator is advanced; `file_path` is only used to
+/// report diagnostics.
+pub fn run_file<'a>(
+    file_path: &'a str,
+    content: &'a str,
+) -> impl Iterator<Item = Token<LiteralType>> + 'a {
+    run(content, move |diagnostic| {
+        error_handler::error(content, file_path, diagnostic)
+    })
 }
 
 /// Run REPL input
@@ -22,27 +31,62 @@ pub fn run_prompt() {
         stdin()
             .read_line(&mut line)
             .expect("Failed to read the line.");
-        run(&line);
+        run(&line, |diagnostic| {
+            error_handler::error(&line, "<stdin>", diagnostic)
+        })
+        .for_each(drop);
     }
 }
 
-/// Run either the source code or REPL line
-fn run(line: &str) -> impl Iterator<Item = Token<LiteralType>> + '_ {
-    let scanner = Scanner::new(line);
-    scanner.scan_tokens().tokens.into_iter()
+/// Run either the source code or REPL line.
+/// Tokens are pulled from the scanner lazily; lexical errors are skipped. Every diagnostic the
+/// scanner records, including warnings and errors that do not interrupt the token stream, is
+/// passed to `report` as soon as the token it was found with is pulled.
+fn run<'a>(
+    line: &'a str,
+    mut report: impl FnMut(&Diagnostic) + 'a,
+) -> impl Iterator<Item = Token<LiteralType>> + 'a {
+    let mut scanner = Scanner::new(line);
+    let mut reported = 0;
+    std::iter::from_fn(move || {
+        let result = scanner.next_ref();
+        scanner.diagnostics[reported..].iter().for_each(&mut report);
+        reported = scanner.diagnostics.len();
+        result
+    })
+    .filter_map(Result::ok)
+    .map(TokenRef::into_token)
 }
 
 #[cfg(test)]
 mod tests {
 
+    use std::fs;
+
     use super::*;
 
     #[test]
     fn test_run_file() {
-        let iterator = run_file("tests/fixtures/program_1.lox");
+        let path = "tests/fixtures/program_1.lox";
+        let content = fs::read_to_string(path).unwrap();
+        let iterator = run_file(path, &content);
         let tokens = iterator.collect::<Vec<Token<LiteralType>>>();
         assert_eq!(tokens.first().unwrap().kind, TokenType::Fun);
         assert_eq!(tokens.last().unwrap().kind, TokenType::RightBrace);
         assert_eq!(tokens.len(), 28);   // This should be 27. Rust adds a [raw] type at the end of the vector; this makes it 27 + 1 = 28.
     }
+
+    #[test]
+    fn test_run_reports_recorded_diagnostics() {
+        let mut messages = vec![];
+        let tokens = run("\"user\u{202E}admin\" 1__0 #", |diagnostic| {
+            messages.push(diagnostic.message.clone())
+        })
+        .collect::<Vec<Token<LiteralType>>>();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].contains("U+202E"));
+        assert!(messages[1].contains("doubled digit separator"));
+        assert!(messages[2].contains("unexpected character"));
+    }
 }
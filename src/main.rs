@@ -0,0 +1,185 @@
+use std::env;
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::process::ExitCode;
+
+use human_panic::setup_panic;
+use log::debug;
+
+use rlox_lexer::serialization::{write_tokens, Format};
+use rlox_lexer::{report, scanner::Scanner, SpannedToken};
+
+const USAGE: &str = "Usage: rlox-lex [--format <table|json|jsonl>] <FILE>...
+
+Tokenizes each Lox source FILE and prints its tokens.
+With FILE as -, the source code is read from the standard input.
+
+Options:
+    --format <FORMAT>   table (default), json (an array of tokens, for a single FILE
+                        only) or jsonl (one token per line)
+
+Exits with status 1 if any diagnostics were produced, and 2 on usage errors or if a file
+could not be read.";
+
+/// Name shown in diagnostics for source code read from the standard input
+const STDIN_NAME: &str = "<stdin>";
+
+fn main() -> ExitCode {
+    setup_panic!();
+    env_logger::init();
+
+    let (format, paths) = match parse_args(env::args().skip(1)) {
+        Some(args) => args,
+        None => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut status = ExitCode::SUCCESS;
+    let mut stdout = io::stdout().lock();
+    for (index, path) in paths.iter().enumerate() {
+        let (file_name, code) = match read_source(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("rlox-lex: {}: {}", path, error);
+                return ExitCode::from(2);
+            }
+        };
+        let result = print_header(&mut stdout, index, paths.len(), format, file_name)
+            .and_then(|_| tokenize(&mut stdout, file_name, &code, format));
+        match result {
+            Ok(true) => {}
+            Ok(false) => status = ExitCode::FAILURE,
+            // The reader went away, e.g. `rlox-lex file.lox | head`; there is nothing left to do
+            Err(error) if error.kind() == ErrorKind::BrokenPipe => return status,
+            Err(error) => {
+                eprintln!("rlox-lex: could not write the tokens: {}", error);
+                return ExitCode::from(2);
+            }
+        }
+    }
+    status
+}
+
+/// Writes the name of the file ahead of its table when several files are tokenized
+fn print_header(
+    out: &mut impl Write,
+    index: usize,
+    files: usize,
+    format: Option<Format>,
+    file_name: &str,
+) -> io::Result<()> {
+    if files == 1 || format.is_some() {
+        return Ok(());
+    }
+    if index > 0 {
+        writeln!(out)?;
+    }
+    writeln!(out, "==> {} <==", file_name)
+}
+
+/// Splits the command-line arguments into the output format (`None` for the table) and the
+/// paths to tokenize. Returns `None` if the arguments are invalid or help was asked for.
+/// JSON output takes a single path, so that the output is one array `read_tokens` can read.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Option<(Option<Format>, Vec<String>)> {
+    let mut format = None;
+    let mut paths = vec![];
+    while let Some(arg) = args.next() {
+        let format_name = if arg == "--format" {
+            args.next()?
+        } else if let Some(name) = arg.strip_prefix("--format=") {
+            String::from(name)
+        } else if arg == "-h" || arg == "--help" {
+            return None;
+        } else {
+            paths.push(arg);
+            continue;
+        };
+        format = match format_name.as_str() {
+            "table" => None,
+            "json" => Some(Format::Json),
+            "jsonl" => Some(Format::JsonLines),
+            _ => return None,
+        };
+    }
+    if paths.is_empty() || (format == Some(Format::Json) && paths.len() > 1) {
+        return None;
+    }
+    Some((format, paths))
+}
+
+/// Reads the source code from the file at `path`, or from the standard input if `path` is `-`
+fn read_source(path: &str) -> io::Result<(&str, String)> {
+    if path == "-" {
+        let mut code = String::new();
+        io::stdin().read_to_string(&mut code)?;
+        Ok((STDIN_NAME, code))
+    } else {
+        Ok((path, fs::read_to_string(path)?))
+    }
+}
+
+/// Writes the tokens of the code string, as a table or serialized, and reports its diagnostics.
+/// Returns `false` if there were any diagnostics.
+fn tokenize(
+    out: &mut impl Write,
+    file_name: &str,
+    code: &str,
+    format: Option<Format>,
+) -> io::Result<bool> {
+    debug!("Tokenizing {} ({} bytes)", file_name, code.len());
+    let scanner = Scanner::new(code).scan_tokens();
+    match format {
+        Some(format) => write_tokens(out, &scanner.tokens, format)?,
+        None => {
+            writeln!(
+                out,
+                "{:<14} {:<24} {:>5} {:>6}  LITERAL",
+                "KIND", "LEXEME", "LINE", "COLUMN"
+            )?;
+            for token in &scanner.tokens {
+                print_token(out, token)?;
+            }
+        }
+    }
+    for diagnostic in &scanner.diagnostics {
+        report(code, file_name, diagnostic);
+    }
+    Ok(scanner.diagnostics.is_empty())
+}
+
+fn print_token(out: &mut impl Write, token: &SpannedToken) -> io::Result<()> {
+    let literal = match &token.literal {
+        Some(literal) => format!("{:?}", literal.kind),
+        None => String::new(),
+    };
+    let row = format!(
+        "{:<14} {:<24} {:>5} {:>6}  {}",
+        format!("{:?}", token.kind),
+        format!("{:?}", token.lexeme),
+        token.span.start_line,
+        token.span.start_column,
+        literal
+    );
+    writeln!(out, "{}", row.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> impl Iterator<Item = String> {
+        args.iter()
+            .map(|arg| String::from(*arg))
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    fn test_json_takes_a_single_file() {
+        assert!(parse_args(args(&["--format", "json", "a.lox"])).is_some());
+        assert!(parse_args(args(&["--format", "json", "a.lox", "b.lox"])).is_none());
+        assert!(parse_args(args(&["--format=jsonl", "a.lox", "b.lox"])).is_some());
+    }
+}
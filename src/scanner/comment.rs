@@ -0,0 +1,78 @@
+use rlox_lib::token::TokenType;
+
+use super::{Scanner, Span};
+
+/// What kind of comment a `Comment` is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentKind {
+    /// A `//` comment
+    Line,
+    /// A `///` comment directly preceding a `fun` or `class` declaration, with only whitespace
+    /// and other doc comments in between
+    Doc,
+    /// A `/* */` comment
+    Block,
+}
+
+/// A comment found in the code string.
+/// Only collected if `LexerConfig::comments` is on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Comment {
+    pub kind: CommentKind,
+    /// Source text of the comment, delimiters included
+    pub text: String,
+    pub span: Span,
+}
+
+impl Comment {
+    /// Text of the comment without its delimiters
+    pub fn content(&self) -> &str {
+        match self.kind {
+            CommentKind::Line => &self.text[2..],
+            CommentKind::Doc => &self.text[3..],
+            CommentKind::Block => {
+                let text = &self.text[2..];
+                text.strip_suffix("*/").unwrap_or(text)
+            }
+        }
+    }
+}
+
+impl<'a> Scanner<'a> {
+    /// Records the comment scanned since `start`, if comments are collected.
+    /// `///` comments are recorded as line comments until the token following them is known.
+    pub(crate) fn collect_comment(&mut self) {
+        if !self.config.comments {
+            return;
+        }
+        let span = self.current_span();
+        let text = &self.code[span.start..span.end];
+        let kind = if text.starts_with("/*") {
+            CommentKind::Block
+        } else {
+            CommentKind::Line
+        };
+        if text.starts_with("///") && !text.starts_with("////") {
+            self.pending_docs.push(self.comments.len());
+        } else {
+            // Anything but whitespace separates doc comments from the declaration
+            self.pending_docs.clear();
+        }
+        self.comments.push(Comment {
+            kind,
+            text: String::from(text),
+            span,
+        });
+    }
+
+    /// Turns the `///` comments right before the token into doc comments if it starts a
+    /// declaration
+    pub(crate) fn classify_docs(&mut self, kind: TokenType) {
+        if matches!(kind, TokenType::Fun | TokenType::Class) {
+            for &index in &self.pending_docs {
+                self.comments[index].kind = CommentKind::Doc;
+            }
+        }
+        self.pending_docs.clear();
+    }
+}
@@ -0,0 +1,94 @@
+use super::Dialect;
+
+/// Options changing how the scanner reads the code string.
+/// The default configuration scans standard Lox.
+///
+/// ```
+/// use rlox_lexer::scanner::{LexerConfig, Scanner};
+///
+/// let config = LexerConfig::new().escapes(true);
+/// let scanner = Scanner::with_config("\"tab\\tseparated\"", config);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LexerConfig {
+    pub(crate) escapes: bool,
+    pub(crate) nested_comments: bool,
+    pub(crate) lossless: bool,
+    pub(crate) comments: bool,
+    pub(crate) dialect: Dialect,
+    pub(crate) unicode_identifiers: bool,
+    pub(crate) exact_numbers: bool,
+    pub(crate) lenient_dots: bool,
+}
+
+impl LexerConfig {
+    /// Creates the default configuration
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes escape sequences (`\"`, `\\`, `\n`, `\t`, `\r`, `\0` and `\u{XXXX}`) in string
+    /// literals. Off by default, in which case a backslash is an ordinary character.
+    pub fn escapes(mut self, escapes: bool) -> Self {
+        self.escapes = escapes;
+        self
+    }
+
+    /// Lets block comments nest, so that `/* a /* b */ c */` is a single comment.
+    /// Off by default, in which case a block comment ends at the first `*/`.
+    pub fn nested_comments(mut self, nested_comments: bool) -> Self {
+        self.nested_comments = nested_comments;
+        self
+    }
+
+    /// Attaches whitespace, line breaks, comments and unscannable text to the tokens as trivia,
+    /// and ends the token stream with an `Eof` token holding the trivia at the end of the code
+    /// string. Concatenating `SpannedToken::full_text` over all tokens then gives back the code
+    /// string byte for byte. Off by default.
+    pub fn lossless(mut self, lossless: bool) -> Self {
+        self.lossless = lossless;
+        self
+    }
+
+    /// Collects comments into `Scanner::comments`, telling `//`, `///` doc and `/* */` comments
+    /// apart. Off by default, in which case comments are skipped.
+    /// `rlox_lib` has no token kind for comments, so they are not yielded as tokens; use
+    /// `Scanner::items` to pull tokens and comments together in source order.
+    pub fn comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Sets the keywords to recognize. Standard Lox by default.
+    pub fn dialect(mut self, dialect: Dialect) -> Self {
+        self.dialect = dialect;
+        self
+    }
+
+    /// Scans identifiers following UAX #31 (XID_Start, then XID_Continue characters) rather than
+    /// only ASCII letters, digits and underscores. Identifiers are normalized to NFC, and warnings
+    /// are recorded for identifiers that mix scripts or look like a keyword or another identifier.
+    /// Off by default.
+    pub fn unicode_identifiers(mut self, unicode_identifiers: bool) -> Self {
+        self.unicode_identifiers = unicode_identifiers;
+        self
+    }
+
+    /// Keeps the exact text of decimal number literals as `Number::Decimal` in
+    /// `SpannedToken::number`, for arbitrary-precision consumers. The token literal still holds
+    /// the nearest float, and no precision diagnostics are reported for it. Values too large for
+    /// a float are reported as a warning instead of an error, as their literal is infinite.
+    /// Off by default.
+    pub fn exact_numbers(mut self, exact_numbers: bool) -> Self {
+        self.exact_numbers = exact_numbers;
+        self
+    }
+
+    /// Accepts number literals starting or ending with their fractional dot, as in `.5` and `5.`,
+    /// with a style warning. Off by default, in which case such dots are scanned as `Dot` tokens,
+    /// as in the reference implementation.
+    pub fn lenient_dots(mut self, lenient_dots: bool) -> Self {
+        self.lenient_dots = lenient_dots;
+        self
+    }
+}
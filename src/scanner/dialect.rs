@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use rlox_lib::token::TokenType;
+
+use crate::constants::KEYWORDS;
+
+/// Token kinds a keyword may be scanned as
+const KEYWORD_KINDS: [TokenType; 16] = [
+    TokenType::And,
+    TokenType::Class,
+    TokenType::Else,
+    TokenType::False,
+    TokenType::Fun,
+    TokenType::For,
+    TokenType::If,
+    TokenType::Nil,
+    TokenType::Or,
+    TokenType::Print,
+    TokenType::Return,
+    TokenType::Super,
+    TokenType::This,
+    TokenType::True,
+    TokenType::Var,
+    TokenType::While,
+];
+
+/// The set of keywords the scanner recognizes, and the token kind each one is scanned as.
+/// Every other identifier-like word is scanned as an `Identifier`.
+///
+/// The default dialect is standard Lox. Dialects are extended by adding and removing keywords,
+/// e.g. to spell `fun` as `fn`:
+///
+/// ```
+/// use rlox_lexer::scanner::{Dialect, LexerConfig};
+/// use rlox_lexer::TokenType;
+///
+/// let dialect = Dialect::lox().without_keyword("fun").with_keyword("fn", TokenType::Fun)?;
+/// let config = LexerConfig::new().dialect(dialect);
+/// # Ok::<(), rlox_lexer::scanner::DialectError>(())
+/// ```
+///
+/// Every keyword must be scanned as a distinct keyword kind. The token kinds come from
+/// `rlox_lib`, which has none for `break` or `continue`, so loop control keywords are rejected
+/// until it does.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dialect {
+    keywords: HashMap<String, TokenType>,
+}
+
+/// Reasons a keyword table is rejected
+#[derive(Debug, Clone, PartialEq)]
+pub enum DialectError {
+    /// The keyword would not be scanned as a single word
+    InvalidKeyword(String),
+    /// The keyword maps to a token kind that is not a keyword kind, e.g. `Identifier`
+    NotAKeywordKind { keyword: String, kind: TokenType },
+    /// Two keywords map to the same token kind
+    DuplicateKind {
+        kind: TokenType,
+        keywords: (String, String),
+    },
+}
+
+impl fmt::Display for DialectError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DialectError::InvalidKeyword(keyword) => {
+                write!(f, "{:?} is not a valid keyword", keyword)
+            }
+            DialectError::NotAKeywordKind { keyword, kind } => {
+                write!(
+                    f,
+                    "keyword {:?} maps to {:?}, which is not a keyword kind",
+                    keyword, kind
+                )
+            }
+            DialectError::DuplicateKind { kind, keywords } => write!(
+                f,
+                "keywords {:?} and {:?} both map to {:?}",
+                keywords.0, keywords.1, kind
+            ),
+        }
+    }
+}
+
+impl std::error::Error for DialectError {}
+
+impl Dialect {
+    /// Standard Lox, with the keywords of `constants::KEYWORDS`
+    pub fn lox() -> Self {
+        Self {
+            keywords: KEYWORDS
+                .entries()
+                .map(|(keyword, kind)| (String::from(*keyword), *kind))
+                .collect(),
+        }
+    }
+
+    /// Creates a dialect with exactly the given keywords
+    pub fn new<'k>(
+        keywords: impl IntoIterator<Item = (&'k str, TokenType)>,
+    ) -> Result<Self, DialectError> {
+        keywords
+            .into_iter()
+            .try_fold(Self::empty(), |dialect, (keyword, kind)| {
+                dialect.with_keyword(keyword, kind)
+            })
+    }
+
+    /// A dialect without any keywords
+    fn empty() -> Self {
+        Self {
+            keywords: HashMap::new(),
+        }
+    }
+
+    /// Adds a keyword, replacing the kind of an existing keyword of the same name
+    pub fn with_keyword(mut self, keyword: &str, kind: TokenType) -> Result<Self, DialectError> {
+        let mut chars = keyword.chars();
+        let valid = chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && chars.all(|c| c.is_alphanumeric() || c == '_');
+        if !valid {
+            return Err(DialectError::InvalidKeyword(String::from(keyword)));
+        }
+        if !KEYWORD_KINDS.contains(&kind) {
+            return Err(DialectError::NotAKeywordKind {
+                keyword: String::from(keyword),
+                kind,
+            });
+        }
+        if let Some((other, _)) = self
+            .keywords
+            .iter()
+            .find(|(other, other_kind)| **other_kind == kind && *other != keyword)
+        {
+            return Err(DialectError::DuplicateKind {
+                kind,
+                keywords: (other.clone(), String::from(keyword)),
+            });
+        }
+        self.keywords.insert(String::from(keyword), kind);
+        Ok(self)
+    }
+
+    /// Removes a keyword, so that it is scanned as an identifier
+    pub fn without_keyword(mut self, keyword: &str) -> Self {
+        self.keywords.remove(keyword);
+        self
+    }
+
+    /// Token kind of the keyword, or `None` if the word is not a keyword
+    pub fn keyword(&self, word: &str) -> Option<TokenType> {
+        self.keywords.get(word).copied()
+    }
+
+    /// All keywords with their token kinds, in no particular order
+    pub fn keywords(&self) -> impl Iterator<Item = (&str, TokenType)> {
+        self.keywords
+            .iter()
+            .map(|(keyword, kind)| (keyword.as_str(), *kind))
+    }
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self::lox()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lox_matches_crafting_interpreters() {
+        let expected = [
+            ("and", TokenType::And),
+            ("class", TokenType::Class),
+            ("else", TokenType::Else),
+            ("false", TokenType::False),
+            ("for", TokenType::For),
+            ("fun", TokenType::Fun),
+            ("if", TokenType::If),
+            ("nil", TokenType::Nil),
+            ("or", TokenType::Or),
+            ("print", TokenType::Print),
+            ("return", TokenType::Return),
+            ("super", TokenType::Super),
+            ("this", TokenType::This),
+            ("true", TokenType::True),
+            ("var", TokenType::Var),
+            ("while", TokenType::While),
+        ];
+        let lox = Dialect::lox();
+        assert_eq!(lox.keywords().count(), expected.len());
+        for (keyword, kind) in expected {
+            assert_eq!(lox.keyword(keyword), Some(kind), "keyword {:?}", keyword);
+        }
+        // The standard table passes its own validation
+        assert_eq!(Dialect::new(lox.keywords()), Ok(lox.clone()));
+    }
+
+    #[test]
+    fn test_rejects_invalid_tables() {
+        assert_eq!(
+            Dialect::lox().with_keyword("either", TokenType::Or),
+            Err(DialectError::DuplicateKind {
+                kind: TokenType::Or,
+                keywords: (String::from("or"), String::from("either")),
+            })
+        );
+        assert!(matches!(
+            Dialect::lox().with_keyword("break", TokenType::Identifier),
+            Err(DialectError::NotAKeywordKind { .. })
+        ));
+        assert_eq!(
+            Dialect::new([("two words", TokenType::And)]),
+            Err(DialectError::InvalidKeyword(String::from("two words")))
+        );
+        // Re-declaring a keyword with its own kind is fine
+        assert!(Dialect::lox().with_keyword("or", TokenType::Or).is_ok());
+    }
+}
@@ -0,0 +1,58 @@
+use crate::error_handler::LexError;
+
+use super::{Comment, Scanner, SpannedToken};
+
+/// A token or a comment, in the order they appear in the code string.
+/// Comments cannot be tokens themselves, as `rlox_lib` has no token kind for them.
+#[derive(Debug)]
+pub enum Item {
+    Token(SpannedToken),
+    Comment(Comment),
+}
+
+impl<'a> Scanner<'a> {
+    /// Scans the next token or comment, see `items()`
+    pub fn next_item(&mut self) -> Option<Result<Item, LexError>> {
+        // The next token is scanned first, so that the comments before it are known and doc
+        // comments are classified by the time they are yielded
+        if self.pending_item.is_none() && !self.items_finished {
+            self.pending_item = self.next();
+            self.items_finished = self.pending_item.is_none();
+        }
+        let next_start = match &self.pending_item {
+            Some(Ok(token)) => token.span.start,
+            Some(Err(error)) => error.span().start,
+            None => usize::MAX,
+        };
+        match self.comments.get(self.yielded_comments) {
+            Some(comment) if comment.span.start < next_start => {
+                self.yielded_comments += 1;
+                Some(Ok(Item::Comment(comment.clone())))
+            }
+            _ => self
+                .pending_item
+                .take()
+                .map(|result| result.map(Item::Token)),
+        }
+    }
+
+    /// Iterates over the remaining tokens and comments in source order, with each comment
+    /// also collected into `comments`. Comments are only yielded if `LexerConfig::comments` is
+    /// on. Mixing this with the other iterators of the same scanner skips items.
+    pub fn items(&mut self) -> Items<'_, 'a> {
+        Items { scanner: self }
+    }
+}
+
+/// Iterator over the tokens and comments of a scanner, see `Scanner::items`
+pub struct Items<'s, 'a> {
+    scanner: &'s mut Scanner<'a>,
+}
+
+impl Iterator for Items<'_, '_> {
+    type Item = Result<Item, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.scanner.next_item()
+    }
+}
@@ -1,28 +1,22 @@
-use std::vec;
+use std::borrow::Cow;
 
-use rlox_lib::token::{Literal, LiteralType, TokenBuilder, TokenType};
+use rlox_lib::token::{Literal, LiteralType, TokenType};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
 
-use super::Scanner;
+use super::security::escape_non_ascii;
+use super::{Position, Scanner, Span, TokenRef};
 
-use crate::constants::{KEYWORDS, NEWLINE};
-use crate::error_handler::ParserError;
+use crate::constants::NEWLINE;
+use crate::error_handler::{Diagnostic, LexError};
 
 impl<'a> Scanner<'a> {
     /// Check two-digit operators
     fn match_char(&mut self, expected: char) -> bool {
-        if self.is_at_end() {
-            return false;
-        }
-        match self.code_chars.peek().map(|&c| c) {
-            Some(character) => {
-                if character == expected {
-                    self.code_chars.next();
-                    return true;
-                }
-                false
-            }
-            _ => false,
+        if self.peek() == Some(expected) {
+            self.advance();
+            return true;
         }
+        false
     }
 
     /// Checks whether a given character is a digit (numerical digit)
@@ -33,402 +27,471 @@ impl<'a> Scanner<'a> {
         }
     }
 
-    /// Checks a partial number value
+    /// Checks a partial number value, digit separators included
     /// e.g. if 126.32 is the number, it checks 126 and 32 separately
-    pub(crate) fn partial_number(&mut self, nvector: &mut Vec<char>) {
-        loop {
-            match self.code_chars.peek().map(|&c| c) {
-                Some(val) => {
-                    if self.is_digit(&val) {
-                        self.current_ptr += 1;
-                        match self.code_chars.next() {
-                            Some(nchar) => {
-                                nvector.push(nchar);
-                            }
-                            _ => {}
-                        }
-                    } else {
-                        break;
-                    }
-                }
-                _ => break,
-            }
-        }
+    pub(crate) fn partial_number(&mut self) {
+        self.advance_while(|byte| byte.is_ascii_digit() || byte == b'_');
     }
 
-    /// Check if the character is a alphabetic character
+    /// Check if the character can start an identifier: an ASCII letter or an underscore,
+    /// or any XID_Start character in Unicode identifier mode
     pub(crate) fn is_alpha(&self, character: &char) -> bool {
-        character.is_alphabetic()
+        if self.config.unicode_identifiers {
+            unicode_ident::is_xid_start(*character) || *character == '_'
+        } else {
+            character.is_ascii_alphabetic() || *character == '_'
+        }
+    }
+
+    /// Check if the character can continue an identifier: an identifier start or a digit,
+    /// or any XID_Continue character in Unicode identifier mode
+    pub(crate) fn is_alpha_numeric(&self, character: &char) -> bool {
+        if self.config.unicode_identifiers {
+            unicode_ident::is_xid_continue(*character)
+        } else {
+            self.is_alpha(character) || self.is_digit(character)
+        }
     }
 
     /// Check numbers
-    /// e.g. 126.32
-    fn number(&mut self, current_char: char) {
-        // while (isDigit(peek())) advance();
-        // 1. Peek the next character
-        // 2. Check whether if it's a digit
-        // 3. If so, continue advancing until the next character is not a character
-        // 4. If we meet a dot, we need to break.
-        let mut nvector: Vec<char> = vec![current_char];
-        self.partial_number(&mut nvector);
-
-        // Look for a fractional part.
-        match self.code_chars.peek().map(|&c| c) {
-            Some(character) => {
-                // Looking for the fractional part
-                if character == '.' {
-                    // Consume the "."
-                    nvector.push(character);
+    /// e.g. 126.32, 6.02E23 or 1.5e-3
+    fn number(&mut self, current_char: char) -> Result<TokenRef<'a>, LexError> {
+        if current_char == '0' {
+            let radix = match self.peek() {
+                Some('x') => Some(16),
+                Some('o') => Some(8),
+                Some('b') => Some(2),
+                _ => None,
+            };
+            if let Some(radix) = radix {
+                self.seek();
+                return self.radix_number(radix);
+            }
+        }
+
+        self.partial_number();
+        let leading_dot = current_char == '.';
+        let mut trailing_dot = false;
+        let mut is_float = leading_dot;
+
+        // Look for a fractional part. As in jlox, the dot is only part of the number if a digit
+        // follows it, so that `3.abs` stays a method call. Lenient dots also accept a trailing
+        // dot, as in `5.`, unless an identifier follows it.
+        if !is_float && self.peek() == Some('.') {
+            let after_dot = self.code[self.current_ptr + 1..].chars().next();
+            if after_dot.is_some_and(|c| self.is_digit(&c)) {
+                // Consume the "."
+                self.seek();
+                self.partial_number();
+                is_float = true;
+            } else if self.config.lenient_dots
+                && !after_dot.is_some_and(|c| self.is_alpha(&c) || c == '.')
+            {
+                self.seek();
+                is_float = true;
+                trailing_dot = true;
+            }
+        }
+
+        // Look for an exponent, which needs at least one digit after its optional sign
+        if matches!(self.peek(), Some('e' | 'E')) {
+            self.seek();
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.seek();
+            }
+            let exponent_start = self.current_ptr;
+            self.partial_number();
+            let exponent = &self.code[exponent_start..self.current_ptr];
+            if !exponent.bytes().any(|byte| byte.is_ascii_digit()) {
+                return Err(LexError::MissingExponentDigits {
+                    lexeme: String::from(self.number_lexeme()),
+                    span: self.current_span(),
+                });
+            }
+            is_float = true;
+        }
+        self.check_separators(10);
+        if leading_dot || trailing_dot {
+            let lexeme = self.number_lexeme();
+            let (message, help) = if leading_dot {
+                ("a leading dot", format!("write it as `0{}`", lexeme))
+            } else {
+                ("a trailing dot", format!("write it as `{}0`", lexeme))
+            };
+            let message = format!("number literal `{}` has {}", lexeme, message);
+            self.record(Diagnostic::warning(message, self.current_span()).with_help(help));
+        }
+
+        let string_value = self.number_lexeme().replace('_', "");
+        let number = self.decimal_number(string_value, is_float)?;
+        Ok(self.number_token(number))
+    }
+
+    /// Scans a hexadecimal (`0x`), octal (`0o`) or binary (`0b`) integer literal; the prefix has
+    /// already been consumed. Every letter and digit following the prefix is part of the literal,
+    /// so that e.g. `0b102` is reported as an invalid digit rather than split into two numbers.
+    fn radix_number(&mut self, radix: u32) -> Result<TokenRef<'a>, LexError> {
+        let mut digits = String::new();
+        let mut invalid_digit = None;
+        while let Some(character) = self.peek() {
+            if !character.is_ascii_alphanumeric() && character != '_' {
+                break;
+            }
+            let position = self.cursor();
+            self.advance();
+            if character == '_' {
+                continue;
+            }
+            if !character.is_digit(radix) && invalid_digit.is_none() {
+                invalid_digit = Some(LexError::InvalidDigit {
+                    digit: character,
+                    radix,
+                    span: self.span_from(position),
+                });
+            }
+            digits.push(character);
+        }
+
+        let lexeme = String::from(&self.code[self.start.ptr..self.current_ptr]);
+        if let Some(error) = invalid_digit {
+            return Err(error);
+        }
+        if digits.is_empty() {
+            return Err(LexError::MissingDigits {
+                lexeme,
+                span: self.current_span(),
+            });
+        }
+        self.check_separators(radix);
+        let number = self.radix_integer(&digits, radix)?;
+        Ok(self.number_token(number))
+    }
+
+    /// Scans a string literal; the opening quote has already been consumed.
+    /// Strings may span several lines; `advance()` keeps `current_line` up to date for every
+    /// embedded newline while the token keeps the line it started on.
+    /// A string without a closing quote is reported as an error covering the rest of the line it
+    /// started on, and scanning resumes from the end of that line.
+    /// The lexeme is the string as written in the source, quotes included, while the literal holds
+    /// its value. With escapes enabled, escape sequences are decoded in the literal only.
+    /// Invalid escapes are recorded as diagnostics and kept as written, once the string is known
+    /// to be terminated; otherwise the rest of the line is scanned again as code.
+    fn string(&mut self) -> Result<Option<TokenRef<'a>>, LexError> {
+        let mut value = String::new();
+        let mut escape_errors = vec![];
+        // Where to resume scanning if the closing quote is never found
+        let mut end_of_first_line = None;
+        let escapes = self.config.escapes;
+
+        loop {
+            // Copy the run of plain characters up to the next quote, escape or newline at once
+            let run_start = self.current_ptr;
+            self.advance_while(|byte| {
+                byte != b'"' && byte != NEWLINE as u8 && !(escapes && byte == b'\\')
+            });
+            value.push_str(&self.code[run_start..self.current_ptr]);
+            match self.peek_byte() {
+                Some(b'"') => {
                     self.seek();
-                    match self.code_chars.peek().map(|&c| c) {
-                        Some(next_char) => {
-                            if self.is_digit(&next_char) {
-                                self.partial_number(&mut nvector);
-                            }
-                        }
-                        _ => {}
+                    break;
+                }
+                Some(b'\\') => {
+                    if let Err(error) = self.escape(&mut value) {
+                        escape_errors.push(error);
+                    }
+                }
+                Some(_) => {
+                    if end_of_first_line.is_none() {
+                        end_of_first_line = Some(self.cursor());
+                    }
+                    self.seek();
+                    value.push(NEWLINE);
+                }
+                None => {
+                    if let Some(position) = end_of_first_line {
+                        self.rewind(position);
                     }
+                    return Err(LexError::UnterminatedString {
+                        span: self.current_span(),
+                    });
                 }
             }
-            _ => {}
         }
 
-        let string_value = String::from_iter(nvector);
-
-        self.tokens.push(
-            TokenBuilder::<LiteralType>::new()
-                .kind(TokenType::Number)
-                .line(self.current_line)
-                .lexeme(string_value.clone())
-                .literal(Some(Literal {
-                    kind: LiteralType::Float(string_value.parse::<f64>().unwrap()),
-                }))
-                .build(),
-        )
+        for error in &escape_errors {
+            self.record(Diagnostic::from(error));
+        }
+        // The lexeme is the exact source text, quotes included
+        Ok(Some(self.make_token(
+            TokenType::String,
+            Some(Literal {
+                kind: LiteralType::String(value),
+            }),
+        )))
     }
 
-    fn string(&mut self, line: usize) -> Result<(), ParserError> {
-        let mut vector: Vec<char> = vec![];
+    /// Decodes the escape sequence starting at the cursor (a backslash) into `value`.
+    /// Invalid sequences are added to `value` as written.
+    fn escape(&mut self, value: &mut String) -> Result<(), LexError> {
+        let start = self.cursor();
+        // Consume the backslash
+        self.seek();
+        let decoded = match self.peek() {
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('0') => '\0',
+            Some('u') => {
+                self.seek();
+                return self.unicode_escape(start, value);
+            }
+            // Leave the newline to the string so that it is tracked like any other
+            Some(NEWLINE) | None => {
+                value.push('\\');
+                return Err(LexError::IncompleteEscape {
+                    span: self.span_from(start),
+                });
+            }
+            Some(character) => {
+                self.seek();
+                value.extend(['\\', character]);
+                return Err(LexError::UnknownEscape {
+                    character,
+                    span: self.span_from(start),
+                });
+            }
+        };
+        self.seek();
+        value.push(decoded);
+        Ok(())
+    }
 
-        loop {
-            match self.code_chars.peek().map(|&c| c) {
-                Some(val) => {
-                    if val == '"' {
+    /// Decodes the `{XXXX}` part of a `\u{XXXX}` escape; `\u` has already been consumed.
+    fn unicode_escape(&mut self, start: Position, value: &mut String) -> Result<(), LexError> {
+        let mut digits = String::new();
+        let mut closed = false;
+        if self.peek() == Some('{') {
+            self.seek();
+            while let Some(character) = self.peek() {
+                if character.is_ascii_hexdigit() {
+                    digits.push(character);
+                    self.seek();
+                } else {
+                    if character == '}' {
                         self.seek();
-                        break;
-                    }
-                    if val == NEWLINE {
-                        self.current_line += 1;
-                        break;
-                    } else {
-                        self.seek_with_add(&mut vector);
+                        closed = true;
                     }
+                    break;
                 }
-                _ => break,
             }
         }
 
-        // If closing quote is not found before eof,
-        if self.is_at_end() {
-            return Err(ParserError {
-                _msg: format!(
-                    "Unterminated string at line: {}, column: {}",
-                    self.current_line, self.current_ptr
-                ),
-            });
+        let decoded = if closed && (1..=6).contains(&digits.len()) {
+            u32::from_str_radix(&digits, 16)
+                .ok()
+                .and_then(char::from_u32)
+        } else {
+            None
+        };
+        match decoded {
+            Some(character) => {
+                value.push(character);
+                Ok(())
+            }
+            None => {
+                let sequence = &self.code[start.ptr..self.current_ptr];
+                value.push_str(sequence);
+                Err(LexError::InvalidUnicodeEscape {
+                    sequence: String::from(sequence),
+                    span: self.span_from(start),
+                })
+            }
         }
+    }
 
-        // If closing quote is found before eof,
-        let string = String::from_iter(vector.iter());
-        self.tokens.push(
-            TokenBuilder::new()
-                .kind(TokenType::String)
-                .lexeme(string)
-                .line(line)
-                .literal(None)
-                .build(),
-        );
+    fn prepare_lexeme_for_id(&self, identifier: &str) -> TokenType {
+        self.config
+            .dialect
+            .keyword(identifier)
+            .unwrap_or(TokenType::Identifier)
+    }
+
+    /// Skips a block comment; the opening `/*` has already been consumed.
+    /// With nested comments enabled, every `/*` inside the comment needs its own `*/`.
+    fn block_comment(&mut self) -> Result<(), LexError> {
+        let mut depth = 1;
+        while depth > 0 {
+            // Only a `*` or a `/` can change the depth
+            self.advance_while(|byte| byte != b'*' && byte != b'/');
+            match self.advance() {
+                Some('*') if self.peek() == Some('/') => {
+                    self.seek();
+                    depth -= 1;
+                }
+                Some('/') if self.config.nested_comments && self.peek() == Some('*') => {
+                    self.seek();
+                    depth += 1;
+                }
+                Some(_) => {}
+                None => {
+                    // Point at the opening delimiter rather than at the end of the code string
+                    return Err(LexError::UnterminatedBlockComment {
+                        span: Span {
+                            start: self.start.ptr,
+                            end: self.start.ptr + 2,
+                            start_line: self.start.line,
+                            start_column: self.start.column,
+                            end_line: self.start.line,
+                            end_column: self.start.column + 2,
+                        },
+                    });
+                }
+            }
+        }
         Ok(())
     }
 
-    fn prepare_lexeme_for_id(&self, identifier: String) -> (TokenType, String) {
-        let id_type = KEYWORDS
-            .get(identifier.as_ref())
-            .unwrap_or_else(|| &TokenType::Identifier);
-        (*id_type, identifier)
+    /// Literal value carried by keyword tokens which stand for a value
+    fn keyword_literal(&self, token_type: TokenType) -> Option<Literal<LiteralType>> {
+        let kind = match token_type {
+            TokenType::True => LiteralType::Boolean(true),
+            TokenType::False => LiteralType::Boolean(false),
+            TokenType::Nil => LiteralType::Nil,
+            _ => return None,
+        };
+        Some(Literal { kind })
     }
 
-    pub(crate) fn identifier(&mut self, line: usize, prev_char: char) {
-        let mut id_vec: Vec<char> = vec![];
-        id_vec.push(prev_char);
+    /// Scans an identifier or keyword; its first character has already been consumed
+    pub(crate) fn identifier(&mut self) -> TokenRef<'a> {
         loop {
-            match self.code_chars.peek().map(|&c| c) {
-                Some(val) => {
-                    if self.is_alpha(&val) {
-                        self.seek_with_add(&mut id_vec);
-                    } else {
-                        break;
-                    }
+            self.advance_while(|byte| byte.is_ascii_alphanumeric() || byte == b'_');
+            // Only Unicode identifiers continue past a non-ASCII character
+            match self.peek() {
+                Some(character) if !character.is_ascii() && self.is_alpha_numeric(&character) => {
+                    self.seek();
                 }
-                _ => {}
+                _ => break,
             }
         }
-        let string = String::from_iter(id_vec.iter());
-        let (token_type, lexeme) = self.prepare_lexeme_for_id(string);
-        self.tokens.push(
-            TokenBuilder::new()
-                .kind(token_type)
-                .lexeme(lexeme.to_string())
-                .line(line)
-                .literal(None)
-                .build(),
+        let mut token = self.make_token(TokenType::Identifier, None);
+        if self.config.unicode_identifiers {
+            token.lexeme = self.normalize_identifier(token.lexeme);
+        }
+        token.kind = self.prepare_lexeme_for_id(&token.lexeme);
+        token.literal = self.keyword_literal(token.kind);
+        if self.config.unicode_identifiers && token.kind == TokenType::Identifier {
+            self.check_confusables(&token.lexeme, token.span);
+        }
+        token
+    }
+
+    /// Brings the identifier into NFC, so that identifiers which look the same are the same name.
+    /// A warning showing both forms is recorded if normalization changed the identifier.
+    fn normalize_identifier(&mut self, identifier: Cow<'a, str>) -> Cow<'a, str> {
+        if is_nfc(&identifier) {
+            return identifier;
+        }
+        let normalized: String = identifier.nfc().collect();
+        self.record(
+            Diagnostic::warning(
+                format!("identifier `{}` is not in NFC form", identifier),
+                self.current_span(),
+            )
+            .with_help(format!(
+                "it is written as `{}` and read as `{}`",
+                escape_non_ascii(&identifier),
+                escape_non_ascii(&normalized)
+            )),
         );
+        Cow::Owned(normalized)
     }
 
-    /// Scans individual characters and returns a token
+    /// Scans individual characters and returns a token.
+    /// `Ok(None)` is returned for characters that do not produce a token (e.g. whitespace, comments).
     pub(crate) fn scan_individual_token(
         &mut self,
         character: &char,
-        line: usize,
-    ) -> Result<(), ParserError> {
+    ) -> Result<Option<TokenRef<'a>>, LexError> {
         match character {
-            '(' => {
-                self.tokens.push(
-                    TokenBuilder::new()
-                        .kind(TokenType::LeftParen)
-                        .lexeme(String::from("("))
-                        .line(line)
-                        .literal(None)
-                        .build(),
-                );
-                Ok(())
-            }
-            ')' => {
-                self.tokens.push(
-                    TokenBuilder::new()
-                        .kind(TokenType::RightParen)
-                        .lexeme(String::from(")"))
-                        .line(line)
-                        .literal(None)
-                        .build(),
-                );
-                Ok(())
-            }
-            '{' => {
-                self.tokens.push(
-                    TokenBuilder::new()
-                        .kind(TokenType::LeftBrace)
-                        .lexeme(String::from("{"))
-                        .line(line)
-                        .literal(None)
-                        .build(),
-                );
-                Ok(())
-            }
-            '}' => {
-                self.tokens.push(
-                    TokenBuilder::new()
-                        .kind(TokenType::RightBrace)
-                        .lexeme(String::from("}"))
-                        .line(line)
-                        .literal(None)
-                        .build(),
-                );
-                Ok(())
-            }
-            ',' => {
-                self.tokens.push(
-                    TokenBuilder::new()
-                        .kind(TokenType::Comma)
-                        .lexeme(String::from(","))
-                        .line(line)
-                        .literal(None)
-                        .build(),
-                );
-                Ok(())
-            }
-            '.' => {
-                self.tokens.push(
-                    TokenBuilder::new()
-                        .kind(TokenType::Dot)
-                        .lexeme(String::from("."))
-                        .line(line)
-                        .literal(None)
-                        .build(),
-                );
-                Ok(())
-            }
-            '-' => {
-                self.tokens.push(
-                    TokenBuilder::new()
-                        .kind(TokenType::Minus)
-                        .lexeme(String::from("-"))
-                        .line(line)
-                        .literal(None)
-                        .build(),
-                );
-                Ok(())
-            }
-            '+' => {
-                self.tokens.push(
-                    TokenBuilder::new()
-                        .kind(TokenType::Plus)
-                        .lexeme(String::from("+"))
-                        .line(line)
-                        .literal(None)
-                        .build(),
-                );
-                Ok(())
-            }
-            ';' => {
-                self.tokens.push(
-                    TokenBuilder::new()
-                        .kind(TokenType::SemiColon)
-                        .lexeme(String::from(";"))
-                        .line(line)
-                        .literal(None)
-                        .build(),
-                );
-                Ok(())
-            }
-            '*' => {
-                self.tokens.push(
-                    TokenBuilder::new()
-                        .kind(TokenType::Star)
-                        .lexeme(String::from("*"))
-                        .line(line)
-                        .literal(None)
-                        .build(),
-                );
-                Ok(())
+            '(' => Ok(Some(self.make_token(TokenType::LeftParen, None))),
+            ')' => Ok(Some(self.make_token(TokenType::RightParen, None))),
+            '{' => Ok(Some(self.make_token(TokenType::LeftBrace, None))),
+            '}' => Ok(Some(self.make_token(TokenType::RightBrace, None))),
+            ',' => Ok(Some(self.make_token(TokenType::Comma, None))),
+            // Lenient dots let a number start with its fractional dot, as in `.5`
+            '.' if self.config.lenient_dots
+                && self.peek_byte().is_some_and(|byte| byte.is_ascii_digit()) =>
+            {
+                self.number('.').map(Some)
             }
+            '.' => Ok(Some(self.make_token(TokenType::Dot, None))),
+            '-' => Ok(Some(self.make_token(TokenType::Minus, None))),
+            '+' => Ok(Some(self.make_token(TokenType::Plus, None))),
+            ';' => Ok(Some(self.make_token(TokenType::SemiColon, None))),
+            '*' => Ok(Some(self.make_token(TokenType::Star, None))),
             '!' => {
                 if self.match_char('=') {
-                    self.tokens.push(
-                        TokenBuilder::new()
-                            .kind(TokenType::BangEqual)
-                            .lexeme(String::from("!="))
-                            .line(line)
-                            .literal(None)
-                            .build(),
-                    );
+                    Ok(Some(self.make_token(TokenType::BangEqual, None)))
                 } else {
-                    self.tokens.push(
-                        TokenBuilder::new()
-                            .kind(TokenType::Bang)
-                            .lexeme(String::from("!"))
-                            .line(line)
-                            .literal(None)
-                            .build(),
-                    );
+                    Ok(Some(self.make_token(TokenType::Bang, None)))
                 }
-                Ok(())
             }
             '=' => {
                 if self.match_char('=') {
-                    self.tokens.push(
-                        TokenBuilder::new()
-                            .kind(TokenType::EqualEqual)
-                            .lexeme(String::from("=="))
-                            .line(line)
-                            .literal(None)
-                            .build(),
-                    );
+                    Ok(Some(self.make_token(TokenType::EqualEqual, None)))
                 } else {
-                    self.tokens.push(
-                        TokenBuilder::new()
-                            .kind(TokenType::Equal)
-                            .lexeme(String::from("="))
-                            .line(line)
-                            .literal(None)
-                            .build(),
-                    );
+                    Ok(Some(self.make_token(TokenType::Equal, None)))
                 }
-                Ok(())
             }
             '<' => {
                 if self.match_char('=') {
-                    self.tokens.push(
-                        TokenBuilder::new()
-                            .kind(TokenType::LessEqual)
-                            .lexeme(String::from("<="))
-                            .line(line)
-                            .literal(None)
-                            .build(),
-                    );
+                    Ok(Some(self.make_token(TokenType::LessEqual, None)))
                 } else {
-                    self.tokens.push(
-                        TokenBuilder::new()
-                            .kind(TokenType::Less)
-                            .lexeme(String::from("<"))
-                            .line(line)
-                            .literal(None)
-                            .build(),
-                    );
+                    Ok(Some(self.make_token(TokenType::Less, None)))
                 }
-                Ok(())
             }
             '>' => {
                 if self.match_char('=') {
-                    self.tokens.push(
-                        TokenBuilder::new()
-                            .kind(TokenType::GreaterEqual)
-                            .lexeme(String::from(">="))
-                            .line(line)
-                            .literal(None)
-                            .build(),
-                    );
+                    Ok(Some(self.make_token(TokenType::GreaterEqual, None)))
                 } else {
-                    self.tokens.push(
-                        TokenBuilder::new()
-                            .kind(TokenType::Greater)
-                            .lexeme(String::from(">"))
-                            .line(line)
-                            .literal(None)
-                            .build(),
-                    );
+                    Ok(Some(self.make_token(TokenType::Greater, None)))
                 }
-                Ok(())
             }
             '/' => {
                 // If the next character is '/', the entire line is ignored.
                 if self.match_char('/') {
                     self.seek_until(NEWLINE);
+                    Ok(None)
+                } else if self.match_char('*') {
+                    self.block_comment().map(|_| None)
                 } else {
-                    self.tokens.push(
-                        TokenBuilder::new()
-                            .kind(TokenType::Slash)
-                            .lexeme(String::from("/"))
-                            .line(line)
-                            .literal(None)
-                            .build(),
-                    )
+                    Ok(Some(self.make_token(TokenType::Slash, None)))
                 }
-                Ok(())
             }
-            ' ' | '\t' | '\r' => Ok(()),
-            &NEWLINE => {
-                self.current_line += 1;
-                Ok(())
+            ' ' | '\t' | '\r' => {
+                // Skip the whole run of whitespace as a single piece of trivia
+                self.advance_while(|byte| matches!(byte, b' ' | b'\t' | b'\r'));
+                Ok(None)
             }
+            // Line and column are moved forward by `advance()`
+            &NEWLINE => Ok(None),
             '"' => {
                 // Iterate until the cursor meets the closing quotes
                 // This loop will terminate when either if 1) closing quotes are met 2) cursor reaches end of code string
-                self.string(line)
+                self.string()
             }
             _ => {
                 if self.is_digit(character) {
-                    self.number(*character);
-                    Ok(())
-                } else if self.is_alpha(&character) {
-                    self.identifier(line, *character);
-                    Ok(())
+                    self.number(*character).map(Some)
+                } else if self.is_alpha(character) {
+                    Ok(Some(self.identifier()))
                 } else {
-                    Err(ParserError {
-                        _msg: format!(
-                            "Unrecognized token: {:?} at line {} column {}",
-                            character, self.current_line, self.current_ptr
-                        ),
+                    Err(LexError::UnexpectedCharacter {
+                        character: *character,
+                        span: self.current_span(),
                     })
                 }
             }
@@ -456,16 +519,16 @@ mod tests {
     fn test_partial_number() {
         let string = String::from("126.32");
         let mut scanner = Scanner::new(&string);
-        let mut resulting_vector: Vec<char> = vec![];
-        scanner.partial_number(&mut resulting_vector);
-        let result = String::from_iter(resulting_vector);
-        assert_eq!(result, "126");
+        scanner.partial_number();
+        assert_eq!(&scanner.code[..scanner.current_ptr], "126");
     }
 
     #[rstest]
     #[case('a', true)]
+    #[case('_', true)]
     #[case('!', false)]
     #[case('3', false)]
+    #[case('é', false)]
     fn test_is_alpha(#[case] input: char, #[case] expected: bool) {
         let mut string = String::from("test");
         string.push('\n');
@@ -1,21 +1,67 @@
+mod comment;
+mod config;
+mod dialect;
+mod item;
 mod lexical_analysis;
+mod number;
+mod security;
+mod span;
+mod token_ref;
+mod trivia;
 
-use log::info;
-
-use rlox_lib::token::LiteralType;
-use crate::Token;
+use crate::constants::NEWLINE;
+use crate::error_handler::{Diagnostic, LexError};
+use rlox_lib::token::TokenType;
+use std::borrow::Cow;
 use std::char;
-use std::iter::Peekable;
-use std::str::Chars;
+use std::collections::HashMap;
+
+pub use comment::{Comment, CommentKind};
+pub use config::LexerConfig;
+pub use dialect::{Dialect, DialectError};
+pub use item::{Item, Items};
+pub use number::Number;
+pub(crate) use span::Position;
+pub use span::{Span, SpannedToken};
+pub use token_ref::TokenRef;
+pub use trivia::{Trivia, TriviaKind};
 
 /// Code is a reference. Current and previous tokens are returned and therefore not referred.
+/// `current_ptr` is the byte offset of the next character in `code`, and `current_line` and
+/// `current_column` are its (1-based) line and column.
+/// The code is scanned as bytes: ASCII characters are read directly from `code.as_bytes()`, and
+/// only the other characters are decoded from UTF-8. `current_ptr` is always on a character
+/// boundary.
 pub struct Scanner<'a> {
     pub code: &'a str,
     pub current_line: usize,
+    pub current_column: usize,
     pub current_ptr: usize,
     pub previous_char: Option<char>,
-    pub code_chars: Peekable<Chars<'a>>,
-    pub tokens: Vec<Token<LiteralType>>,
+    pub tokens: Vec<SpannedToken>,
+    /// Every problem found in the code string so far, in the order they were found.
+    /// Problems that do not prevent a token from being produced (e.g. an invalid escape sequence
+    /// inside an otherwise valid string) are only recorded here, not yielded as errors.
+    pub diagnostics: Vec<Diagnostic>,
+    /// Every comment found in the code string so far, if `LexerConfig::comments` is on
+    pub comments: Vec<Comment>,
+    pub(crate) config: LexerConfig,
+    /// Where the token being scanned starts
+    pub(crate) start: Position,
+    /// Trivia scanned since the last token, in lossless mode
+    pub(crate) pending_trivia: Vec<Trivia>,
+    /// Whether the `Eof` token ending a lossless token stream has been yielded
+    pub(crate) finished: bool,
+    /// Indices into `comments` of the `///` comments since the last token
+    pub(crate) pending_docs: Vec<usize>,
+    /// Token scanned ahead by `next_item()`, to be yielded after the comments before it
+    pub(crate) pending_item: Option<Result<SpannedToken, LexError>>,
+    /// Whether `next_item()` has scanned all tokens
+    pub(crate) items_finished: bool,
+    /// Number of `comments` yielded by `next_item()`
+    pub(crate) yielded_comments: usize,
+    /// First identifier seen for each confusable skeleton, in Unicode identifier mode
+    pub(crate) identifiers: HashMap<String, (String, Span)>,
 }
 
 /// We need to guarantee that the reference `code` we provide into `new()` lives throughout the Scanner instance.
@@ -23,60 +69,237 @@ pub struct Scanner<'a> {
 impl<'a> Scanner<'a> {
     /// Creates a new Scanner struct
     pub fn new(code: &'a str) -> Scanner<'a> {
+        Self::with_config(code, LexerConfig::default())
+    }
+
+    /// Creates a new Scanner struct that scans according to the given configuration
+    pub fn with_config(code: &'a str, config: LexerConfig) -> Scanner<'a> {
         Self {
             code,
             current_line: 1,
+            current_column: 1,
             current_ptr: 0,
             previous_char: None,
-            code_chars: code.chars().peekable(),
             tokens: vec![],
+            diagnostics: vec![],
+            comments: vec![],
+            config,
+            start: Position {
+                ptr: 0,
+                line: 1,
+                column: 1,
+            },
+            pending_trivia: vec![],
+            finished: false,
+            pending_docs: vec![],
+            pending_item: None,
+            items_finished: false,
+            yielded_comments: 0,
+            identifiers: HashMap::new(),
         }
     }
 
-    /// Seeks the code string by one character.
-    pub(crate) fn seek(&mut self) {
-        self.current_ptr += 1;
-        self.code_chars.next();
+    /// Byte at the cursor
+    pub(crate) fn peek_byte(&self) -> Option<u8> {
+        self.code.as_bytes().get(self.current_ptr).copied()
+    }
+
+    /// Character at the cursor, decoded from UTF-8 only if it is not ASCII
+    pub(crate) fn peek(&self) -> Option<char> {
+        match self.peek_byte()? {
+            byte if byte.is_ascii() => Some(char::from(byte)),
+            _ => self.code[self.current_ptr..].chars().next(),
+        }
+    }
+
+    /// Consumes the next character.
+    /// Every character of the code string must be consumed through here or `advance_while()` so
+    /// that the byte offset, line and column of the cursor stay in sync.
+    pub(crate) fn advance(&mut self) -> Option<char> {
+        let character = self.peek()?;
+        self.current_ptr += character.len_utf8();
+        if character == NEWLINE {
+            self.current_line += 1;
+            self.current_column = 1;
+        } else {
+            self.current_column += 1;
+        }
+        Some(character)
     }
 
-    /// Seek with adding the current character to a given vector
-    pub(crate) fn seek_with_add(&mut self, vector: &mut Vec<char>) {
-        self.current_ptr += 1;
-        match self.code_chars.next() {
-            Some(c) => {
-                (*vector).push(c);
+    /// Consumes bytes while the predicate holds for them, without decoding any characters.
+    /// The predicate must give the same answer for every non-ASCII byte, so that the cursor stops
+    /// on a character boundary: ASCII bytes never occur inside a multi-byte UTF-8 sequence.
+    pub(crate) fn advance_while(&mut self, predicate: impl Fn(u8) -> bool) {
+        let bytes = self.code.as_bytes();
+        while let Some(&byte) = bytes.get(self.current_ptr) {
+            if !predicate(byte) {
+                break;
             }
-            _ => {}
+            self.current_ptr += 1;
+            if byte == NEWLINE as u8 {
+                self.current_line += 1;
+                self.current_column = 1;
+            } else if !is_utf8_continuation(byte) {
+                // A character takes a column at its first byte
+                self.current_column += 1;
+            }
+        }
+    }
+
+    /// Position of the cursor
+    pub(crate) fn cursor(&self) -> Position {
+        Position {
+            ptr: self.current_ptr,
+            line: self.current_line,
+            column: self.current_column,
         }
     }
 
+    /// Moves the cursor back to an earlier position.
+    /// Everything scanned after that position will be scanned again.
+    pub(crate) fn rewind(&mut self, position: Position) {
+        self.current_ptr = position.ptr;
+        self.current_line = position.line;
+        self.current_column = position.column;
+    }
+
+    /// Seeks the code string by one character.
+    pub(crate) fn seek(&mut self) {
+        self.advance();
+    }
+
     /// Seek until a certail terminal_char character.
     pub(crate) fn seek_until(&mut self, terminal_char: char) {
-        while !self.is_at_end() && self.code_chars.peek().map(|&c| c).unwrap() != terminal_char {
+        if terminal_char.is_ascii() {
+            let terminal = terminal_char as u8;
+            self.advance_while(|byte| byte != terminal);
+            return;
+        }
+        while self
+            .peek()
+            .is_some_and(|character| character != terminal_char)
+        {
             self.seek();
         }
     }
 
-    /// Returns an iterator that contains tokens of type `Token`.
-    /// Chained token is the EOF token that makes parsing a little easier.
+    /// Span from the given position up to the cursor
+    pub(crate) fn span_from(&self, position: Position) -> Span {
+        Span {
+            start: position.ptr,
+            end: self.current_ptr,
+            start_line: position.line,
+            start_column: position.column,
+            end_line: self.current_line,
+            end_column: self.current_column,
+        }
+    }
+
+    /// Span from the start of the token being scanned up to the cursor
+    pub(crate) fn current_span(&self) -> Span {
+        self.span_from(self.start)
+    }
+
+    /// Records a problem that does not interrupt the token stream
+    pub(crate) fn record(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    /// Scans the whole code string eagerly and stores the resulting tokens in `tokens`.
+    /// Lexical errors do not stop the scan; they are available in `diagnostics` afterwards.
     /// This is not an associated function, as it does have `self` in it. This needs to be called
     /// as a method. scan_tokens() takes in an exclusive reference (i.e., mut self) to the instance of Scanner
     /// because there's no need to invoke any other scanner functions after the invocation of this function.
+    /// Use the `Iterator` implementation instead to pull tokens on demand.
     pub fn scan_tokens(mut self) -> Self {
+        let tokens = self.by_ref().filter_map(Result::ok).collect();
+        self.tokens = tokens;
+        self
+    }
+
+    /// Scans the next token without copying its lexeme, see `TokenRef`.
+    /// Trivia is only attached to owned tokens, so it is dropped here even in lossless mode.
+    pub fn next_ref(&mut self) -> Option<Result<TokenRef<'a>, LexError>> {
+        let token = self.scan_token();
+        self.pending_trivia.clear();
+        token
+    }
+
+    /// Iterates over the remaining tokens without copying their lexemes, see `next_ref()`
+    pub fn token_refs(&mut self) -> impl Iterator<Item = Result<TokenRef<'a>, LexError>> + '_ {
+        std::iter::from_fn(move || self.next_ref())
+    }
+
+    /// Turns the token just scanned into an owned token with, in lossless mode, its trivia
+    fn spanned(&mut self, mut token: TokenRef<'a>) -> SpannedToken {
+        let span = token.span;
+        let raw_lexeme = matches!(token.lexeme, Cow::Owned(_))
+            .then(|| String::from(&self.code[span.start..span.end]));
+        let leading_trivia = std::mem::take(&mut self.pending_trivia);
+        let trailing_trivia = if self.config.lossless && token.kind != TokenType::Eof {
+            self.trailing_trivia()
+        } else {
+            vec![]
+        };
+        let number = token.number.take();
+        SpannedToken {
+            token: token.into_token(),
+            span,
+            raw_lexeme,
+            number,
+            leading_trivia,
+            trailing_trivia,
+        }
+    }
+
+    /// Scans the next token, skipping (and in lossless mode collecting) trivia on the way.
+    /// Tokens are produced lazily; each call consumes only as much of the code string as is
+    /// needed to recognize the next token (or lexical error).
+    /// Errors returned here are also recorded in `diagnostics`.
+    fn scan_token(&mut self) -> Option<Result<TokenRef<'a>, LexError>> {
         while !self.is_at_end() {
-            let current_character = self.code_chars.next();
-            if let Some(character) = current_character {
-                match self.scan_individual_token(&character, self.current_line) {
-                    Ok(()) => (),
-                    Err(error) => {
-                        info!("Error: {:?}", error);
+            self.start = self.cursor();
+            let current_character = self.advance();
+            let result = match current_character {
+                Some(character) => self.scan_individual_token(&character),
+                None => Ok(None),
+            };
+            self.previous_char = current_character;
+            // A stray invisible character is reported as unexpected already
+            if !matches!(result, Err(LexError::UnexpectedCharacter { .. })) {
+                self.check_invisible_characters();
+            }
+            match result {
+                Ok(Some(token)) => {
+                    self.classify_docs(token.kind);
+                    return Some(Ok(token));
+                }
+                Ok(None) => {
+                    let kind = self.trivia_kind();
+                    if matches!(kind, TriviaKind::LineComment | TriviaKind::BlockComment) {
+                        self.collect_comment();
+                    }
+                    if self.config.lossless {
+                        self.push_trivia(kind);
                     }
                 }
+                Err(error) => {
+                    self.record(Diagnostic::from(&error));
+                    if self.config.lossless {
+                        self.push_trivia(TriviaKind::Skipped);
+                    }
+                    return Some(Err(error));
+                }
             }
-            self.current_ptr += 1;
-            self.previous_char = current_character;
         }
-        self
+        if self.config.lossless && !self.finished {
+            // The trivia at the end of the code string is kept by an empty `Eof` token
+            self.finished = true;
+            self.start = self.cursor();
+            return Some(Ok(self.make_token(TokenType::Eof, None)));
+        }
+        None
     }
 
     /// Checks if the cursor is at end
@@ -85,6 +308,22 @@ impl<'a> Scanner<'a> {
     }
 }
 
+/// Whether the byte continues a multi-byte UTF-8 sequence rather than starting a character
+fn is_utf8_continuation(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
+
+/// Tokens are produced lazily, see `scan_token()`.
+/// Errors yielded here are also recorded in `diagnostics`.
+impl<'a> Iterator for Scanner<'a> {
+    type Item = Result<SpannedToken, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.scan_token()
+            .map(|result| result.map(|token| self.spanned(token)))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,26 +333,78 @@ mod tests {
         let mut scanner = Scanner::new("abc");
         scanner.seek();
         assert_eq!(scanner.current_ptr, 1);
-        assert_eq!(scanner.code_chars.peek(), Some(&'b'));
+        assert_eq!(scanner.peek(), Some('b'));
     }
 
     #[test]
-    fn test_seek_with_add() {
-        let mut scanner = Scanner::new("abc");
-        let mut vector = Vec::new();
-        scanner.seek_with_add(&mut vector);
-        assert_eq!(vector, vec!['a']);
+    fn test_seek_until() {
+        let mut scanner = Scanner::new("abcdef");
+        scanner.seek_until('d');
+        // After seeking until 'd', the next character should be 'e', as 'd' is consumed
+        assert_eq!(scanner.current_ptr, 3);
+        assert_eq!(scanner.peek(), Some('d'));
+    }
+
+    #[test]
+    fn test_next_yields_tokens_lazily() {
+        let mut scanner = Scanner::new("( )");
+        let token = scanner.next().unwrap().unwrap();
+        assert_eq!(token.lexeme, "(");
+        // Only the first character has been consumed
         assert_eq!(scanner.current_ptr, 1);
-        assert_eq!(scanner.code_chars.peek(), Some(&'b'));
+        assert_eq!(token.span.start, 0);
+        assert_eq!(token.span.end, 1);
+        assert_eq!(scanner.next().unwrap().unwrap().lexeme, ")");
+        assert!(scanner.next().is_none());
     }
 
     #[test]
-    fn test_seek_until() {
-        let mut scanner = Scanner::new("abcdef");
+    fn test_advance_tracks_bytes_lines_and_columns() {
+        let mut scanner = Scanner::new("é\nb");
+        scanner.advance();
+        assert_eq!(scanner.current_ptr, 2);
+        assert_eq!(scanner.current_column, 2);
+        scanner.advance();
+        assert_eq!(scanner.current_ptr, 3);
+        assert_eq!(scanner.current_line, 2);
+        assert_eq!(scanner.current_column, 1);
+    }
+
+    #[test]
+    fn test_advance_while_counts_characters() {
+        let mut scanner = Scanner::new("aé日\nb🦀c;");
+        scanner.advance_while(|byte| byte != b';');
+        assert_eq!(scanner.current_ptr, "aé日\nb🦀c".len());
+        assert_eq!(scanner.current_line, 2);
+        // `b`, the crab and `c` take a column each
+        assert_eq!(scanner.current_column, 4);
+        assert_eq!(scanner.peek(), Some(';'));
+    }
+
+    #[test]
+    fn test_peek_decodes_non_ascii() {
+        let mut scanner = Scanner::new("é🦀");
+        assert_eq!(scanner.peek(), Some('é'));
+        assert_eq!(scanner.peek_byte(), Some(0xC3));
+        scanner.advance();
+        assert_eq!(scanner.advance(), Some('🦀'));
+        assert_eq!(scanner.peek(), None);
+    }
+
+    #[test]
+    fn test_rewind() {
+        let mut scanner = Scanner::new("ab\ncd");
         scanner.seek_until('d');
-        // After seeking until 'd', the next character should be 'e', as 'd' is consumed
+        scanner.rewind(Position {
+            ptr: 2,
+            line: 1,
+            column: 3,
+        });
+        assert_eq!(scanner.current_line, 1);
+        assert_eq!(scanner.peek(), Some('\n'));
+        scanner.advance();
+        assert_eq!(scanner.current_line, 2);
         assert_eq!(scanner.current_ptr, 3);
-        assert_eq!(scanner.code_chars.peek(), Some(&'d'));
     }
 
     #[test]
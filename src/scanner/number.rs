@@ -0,0 +1,214 @@
+use rlox_lib::token::{Literal, LiteralType, TokenType};
+
+use super::{Scanner, Span, TokenRef};
+use crate::error_handler::{Diagnostic, LexError};
+
+/// Largest integer up to which every integer is exactly representable as an `f64`
+const EXACT_FLOAT_INTEGERS: u128 = 1 << 53;
+
+/// Value of a number literal, telling integers and floats apart.
+/// `LiteralType` only has `Float`, so number tokens always carry their value as an `f64`;
+/// `SpannedToken::number` keeps the exact value for interpreters doing integer arithmetic.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Number {
+    /// A literal without fractional part or exponent, in any radix
+    Integer(u64),
+    Float(f64),
+    /// The exact text of a decimal literal, digit separators removed, for arbitrary-precision
+    /// consumers. Only produced with `LexerConfig::exact_numbers`.
+    Decimal(String),
+}
+
+impl Number {
+    /// The value as carried by the token literal
+    pub fn as_f64(&self) -> f64 {
+        match self {
+            Number::Integer(value) => *value as f64,
+            Number::Float(value) => *value,
+            Number::Decimal(text) => text.parse().unwrap_or(f64::NAN),
+        }
+    }
+}
+
+/// Significant digits of a decimal number and the power of ten of its last digit, so that
+/// numbers can be compared however they are written, e.g. `("15", -1)` for both `1.50` and
+/// `0.015e2`. Zero has no significant digits.
+fn significant_digits(text: &str) -> (String, i64) {
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse().unwrap_or(0)),
+        None => (text, 0),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", integer, fraction);
+    let trimmed = digits.trim_end_matches('0');
+    let exponent = exponent - fraction.len() as i64 + (digits.len() - trimmed.len()) as i64;
+    match trimmed.trim_start_matches('0') {
+        "" => (String::new(), 0),
+        significant => (String::from(significant), exponent),
+    }
+}
+
+impl<'a> Scanner<'a> {
+    /// Builds the token for the number literal scanned since `start`
+    pub(crate) fn number_token(&mut self, number: Number) -> TokenRef<'a> {
+        let literal = Literal {
+            kind: LiteralType::Float(number.as_f64()),
+        };
+        let mut token = self.make_token(TokenType::Number, Some(literal));
+        token.number = Some(number);
+        token
+    }
+
+    /// Source text of the number literal scanned since `start`
+    pub(crate) fn number_lexeme(&self) -> &'a str {
+        &self.code[self.start.ptr..self.current_ptr]
+    }
+
+    /// Value of the decimal number literal scanned since `start`, given its text without digit
+    /// separators. Values a float cannot hold exactly are reported as warnings and values too
+    /// large for a float as an error. Exact numbers are only checked for being too large for the
+    /// token's float literal, which is reported as a warning.
+    pub(crate) fn decimal_number(
+        &mut self,
+        digits: String,
+        is_float: bool,
+    ) -> Result<Number, LexError> {
+        // Integers are parsed as such; only the ones too large for a `u128` need a float parse
+        if !is_float && !self.config.exact_numbers {
+            if let Ok(integer) = digits.parse::<u128>() {
+                self.check_integer_precision(integer);
+                return Ok(match u64::try_from(integer) {
+                    Ok(integer) => Number::Integer(integer),
+                    Err(_) => Number::Float(integer as f64),
+                });
+            }
+        }
+        let value = match digits.parse::<f64>() {
+            Ok(value) => value,
+            Err(_) => {
+                return Err(LexError::MalformedNumber {
+                    lexeme: digits,
+                    span: self.current_span(),
+                })
+            }
+        };
+        if self.config.exact_numbers {
+            if value.is_infinite() {
+                let message = format!(
+                    "number literal `{}` is too large for a float",
+                    self.number_lexeme()
+                );
+                self.record(Diagnostic::warning(message, self.current_span()).with_help(
+                    String::from(
+                        "the token literal is infinite; `SpannedToken::number` keeps the exact \
+                         text",
+                    ),
+                ));
+            }
+            return Ok(Number::Decimal(digits));
+        }
+        if value.is_infinite() {
+            return Err(LexError::NumberOverflow {
+                lexeme: String::from(self.number_lexeme()),
+                span: self.current_span(),
+            });
+        }
+        let written = significant_digits(&digits);
+        if value == 0.0 && !written.0.is_empty() {
+            let message = format!("number literal `{}` rounds to zero", self.number_lexeme());
+            self.record(
+                Diagnostic::warning(message, self.current_span()).with_help(String::from(
+                    "the smallest positive number is about 4.9e-324",
+                )),
+            );
+        } else if written != significant_digits(&format!("{:e}", value)) {
+            // The shortest text reading back as the float differs from the literal
+            let message = format!(
+                "number literal `{}` is rounded to {} as a float",
+                self.number_lexeme(),
+                value
+            );
+            self.record(
+                Diagnostic::warning(message, self.current_span()).with_help(String::from(
+                    "floats hold 15 to 17 significant decimal digits",
+                )),
+            );
+        }
+        Ok(Number::Float(value))
+    }
+
+    /// Value of the hexadecimal, octal or binary literal scanned since `start`, given its digits
+    /// without prefix and digit separators
+    pub(crate) fn radix_integer(&mut self, digits: &str, radix: u32) -> Result<Number, LexError> {
+        match u64::from_str_radix(digits, radix) {
+            Ok(value) => {
+                self.check_integer_precision(value.into());
+                Ok(Number::Integer(value))
+            }
+            Err(_) => Err(LexError::NumberOverflow {
+                lexeme: String::from(self.number_lexeme()),
+                span: self.current_span(),
+            }),
+        }
+    }
+
+    /// Warns if the integer literal scanned since `start` is rounded in the token's float literal
+    fn check_integer_precision(&mut self, value: u128) {
+        if value <= EXACT_FLOAT_INTEGERS || (value as f64) as u128 == value {
+            return;
+        }
+        let message = format!(
+            "integer literal `{}` is rounded to {} as a float",
+            self.number_lexeme(),
+            value as f64
+        );
+        self.record(
+            Diagnostic::warning(message, self.current_span()).with_help(String::from(
+                "floats only hold integers up to 2^53 exactly; `SpannedToken::number` keeps the \
+                 exact value of integers that fit in 64 bits",
+            )),
+        );
+    }
+
+    /// Records an error for every `_` digit separator in the number literal scanned since `start`
+    /// that does not sit between two digits of the given radix.
+    /// The literal is still scanned as if the separator was not there.
+    pub(crate) fn check_separators(&mut self, radix: u32) {
+        let lexeme = &self.code[self.start.ptr..self.current_ptr];
+        let mut diagnostics = vec![];
+        let mut offset = 0;
+        while let Some(found) = lexeme[offset..].find('_') {
+            let first = offset + found;
+            let last =
+                first + lexeme[first..].len() - lexeme[first..].trim_start_matches('_').len();
+            let is_digit = |c: Option<char>| c.is_some_and(|c| c.is_digit(radix));
+            let problem = if !is_digit(lexeme[..first].chars().next_back()) {
+                "leading"
+            } else if !is_digit(lexeme[last..].chars().next()) {
+                "trailing"
+            } else if last - first > 1 {
+                "doubled"
+            } else {
+                offset = last;
+                continue;
+            };
+            let span = Span {
+                start: self.start.ptr + first,
+                end: self.start.ptr + last,
+                start_line: self.start.line,
+                start_column: self.start.column + first,
+                end_line: self.start.line,
+                end_column: self.start.column + last,
+            };
+            diagnostics.push(
+                Diagnostic::error(format!("{} digit separator", problem), span).with_help(
+                    String::from("`_` can only separate two digits, as in `1_000_000`"),
+                ),
+            );
+            offset = last;
+        }
+        for diagnostic in diagnostics {
+            self.record(diagnostic);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use unicode_security::{skeleton, MixedScript};
+
+use super::{Scanner, Span};
+use crate::constants::NEWLINE;
+use crate::error_handler::Diagnostic;
+
+/// Characters that are invisible or reorder the text around them when displayed, so that code
+/// can read differently from how it is scanned ("Trojan Source", CVE-2021-42574)
+const INVISIBLE_CHARACTERS: [(char, &str); 18] = [
+    ('\u{061C}', "ARABIC LETTER MARK"),
+    ('\u{180E}', "MONGOLIAN VOWEL SEPARATOR"),
+    ('\u{200B}', "ZERO WIDTH SPACE"),
+    ('\u{200C}', "ZERO WIDTH NON-JOINER"),
+    ('\u{200D}', "ZERO WIDTH JOINER"),
+    ('\u{200E}', "LEFT-TO-RIGHT MARK"),
+    ('\u{200F}', "RIGHT-TO-LEFT MARK"),
+    ('\u{202A}', "LEFT-TO-RIGHT EMBEDDING"),
+    ('\u{202B}', "RIGHT-TO-LEFT EMBEDDING"),
+    ('\u{202C}', "POP DIRECTIONAL FORMATTING"),
+    ('\u{202D}', "LEFT-TO-RIGHT OVERRIDE"),
+    ('\u{202E}', "RIGHT-TO-LEFT OVERRIDE"),
+    ('\u{2060}', "WORD JOINER"),
+    ('\u{2066}', "LEFT-TO-RIGHT ISOLATE"),
+    ('\u{2067}', "RIGHT-TO-LEFT ISOLATE"),
+    ('\u{2068}', "FIRST STRONG ISOLATE"),
+    ('\u{2069}', "POP DIRECTIONAL ISOLATE"),
+    ('\u{FEFF}', "ZERO WIDTH NO-BREAK SPACE"),
+];
+
+/// Name of the character if it is a bidirectional control or zero-width character
+fn invisible_character_name(character: char) -> Option<&'static str> {
+    INVISIBLE_CHARACTERS
+        .iter()
+        .find(|(invisible, _)| *invisible == character)
+        .map(|(_, name)| *name)
+}
+
+/// Spells out every non-ASCII character of the text as a `\u{...}` escape, so that look-alike
+/// characters can be told apart in diagnostics
+pub(crate) fn escape_non_ascii(text: &str) -> String {
+    text.chars()
+        .map(|c| {
+            if c.is_ascii() {
+                c.to_string()
+            } else {
+                c.escape_unicode().to_string()
+            }
+        })
+        .collect()
+}
+
+impl<'a> Scanner<'a> {
+    /// Records an error for every bidirectional control and zero-width character scanned since
+    /// `start`, i.e. inside the string, comment or identifier just scanned. The token is still
+    /// produced; the error only keeps such code from passing unnoticed.
+    pub(crate) fn check_invisible_characters(&mut self) {
+        let text = &self.code[self.start.ptr..self.current_ptr];
+        if text.is_ascii() {
+            return;
+        }
+        let place = if text.starts_with('"') {
+            "string literal"
+        } else if text.starts_with('/') {
+            "comment"
+        } else {
+            "identifier"
+        };
+        let (mut line, mut column) = (self.start.line, self.start.column);
+        let mut diagnostics = vec![];
+        for (offset, character) in text.char_indices() {
+            if let Some(name) = invisible_character_name(character) {
+                let start = self.start.ptr + offset;
+                let span = Span {
+                    start,
+                    end: start + character.len_utf8(),
+                    start_line: line,
+                    start_column: column,
+                    end_line: line,
+                    end_column: column + 1,
+                };
+                let mut diagnostic = Diagnostic::error(
+                    format!(
+                        "invisible character U+{:04X} ({}) in {}",
+                        character as u32, name, place
+                    ),
+                    span,
+                )
+                .with_help(String::from(
+                    "it can make the code display differently from how it is read",
+                ));
+                if place == "string literal" && self.config.escapes {
+                    diagnostic = diagnostic.with_help(format!(
+                        "write it as `\\u{{{:x}}}` if it is intended",
+                        character as u32
+                    ));
+                }
+                diagnostics.push(diagnostic);
+            }
+            if character == NEWLINE {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        for diagnostic in diagnostics {
+            self.record(diagnostic);
+        }
+    }
+
+    /// Warns about an identifier that mixes scripts, or that looks like a keyword or another
+    /// identifier seen before (UTS #39 confusable detection). Only look-alikes involving a
+    /// non-ASCII character are reported, so plain ASCII code such as `l` next to `I` is left alone.
+    pub(crate) fn check_confusables(&mut self, identifier: &str, span: Span) {
+        let ascii = identifier.is_ascii();
+        if !ascii && !identifier.is_single_script() {
+            self.record(
+                Diagnostic::warning(format!("identifier `{}` mixes scripts", identifier), span)
+                    .with_help(format!("it is spelled `{}`", escape_non_ascii(identifier))),
+            );
+        }
+
+        let identifier_skeleton: String = skeleton(identifier).collect();
+        if !ascii {
+            let keyword = self
+                .config
+                .dialect
+                .keywords()
+                .find(|(keyword, _)| skeleton(keyword).eq(identifier_skeleton.chars()))
+                .map(|(keyword, _)| String::from(keyword));
+            if let Some(keyword) = keyword {
+                self.record(
+                    Diagnostic::warning(
+                        format!(
+                            "identifier `{}` looks like the keyword `{}`",
+                            identifier, keyword
+                        ),
+                        span,
+                    )
+                    .with_help(format!("it is spelled `{}`", escape_non_ascii(identifier))),
+                );
+                return;
+            }
+        }
+
+        match self.identifiers.get(&identifier_skeleton) {
+            Some((other, other_span)) if other != identifier && !(ascii && other.is_ascii()) => {
+                let diagnostic = Diagnostic::warning(
+                    format!(
+                        "identifier `{}` looks like `{}` from line {}, column {}",
+                        identifier, other, other_span.start_line, other_span.start_column
+                    ),
+                    span,
+                )
+                .with_help(format!(
+                    "they are spelled `{}` and `{}`",
+                    escape_non_ascii(identifier),
+                    escape_non_ascii(other)
+                ));
+                self.record(diagnostic);
+            }
+            Some(_) => {}
+            None => {
+                self.identifiers
+                    .insert(identifier_skeleton, (String::from(identifier), span));
+            }
+        }
+    }
+}
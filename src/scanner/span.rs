@@ -0,0 +1,88 @@
+use std::ops::Deref;
+
+use rlox_lib::token::LiteralType;
+
+use super::{Number, Trivia};
+use crate::Token;
+
+/// A region of the code string.
+/// `start` and `end` are byte offsets into the code string (`end` is exclusive), so
+/// `&code[span.start..span.end]` is the exact source text of the region.
+/// Lines and columns are 1-based. Columns are counted in characters, and the end column is
+/// the column right after the last character of the region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Span {
+    /// Length of the region in bytes
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Checks if the region covers no characters at all
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// A point in the code string: a byte offset with its (1-based) line and column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Position {
+    pub(crate) ptr: usize,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+}
+
+/// A token together with the region of the code string it was scanned from.
+/// Dereferences into the underlying `Token`, so `spanned.kind` and `spanned.lexeme` work as usual.
+/// In lossless mode the token also owns the trivia around it; otherwise both lists are empty.
+#[derive(Debug)]
+pub struct SpannedToken {
+    pub token: Token<LiteralType>,
+    pub span: Span,
+    /// Source text of the token if it differs from its lexeme, i.e. for identifiers changed by
+    /// NFC normalization
+    pub raw_lexeme: Option<String>,
+    /// Exact value of a number literal, telling integers and floats apart
+    pub number: Option<Number>,
+    /// Trivia between the previous token's trailing trivia and this token
+    pub leading_trivia: Vec<Trivia>,
+    /// Trivia after this token up to and including the end of its line, block comments excepted
+    pub trailing_trivia: Vec<Trivia>,
+}
+
+impl SpannedToken {
+    /// Drops the span and returns the underlying token
+    pub fn into_token(self) -> Token<LiteralType> {
+        self.token
+    }
+
+    /// The token's source text surrounded by its trivia.
+    /// Concatenating this over a lossless token stream reproduces the code string.
+    pub fn full_text(&self) -> String {
+        let mut text = String::new();
+        for trivia in &self.leading_trivia {
+            text.push_str(&trivia.text);
+        }
+        text.push_str(self.raw_lexeme.as_ref().unwrap_or(&self.lexeme));
+        for trivia in &self.trailing_trivia {
+            text.push_str(&trivia.text);
+        }
+        text
+    }
+}
+
+impl Deref for SpannedToken {
+    type Target = Token<LiteralType>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.token
+    }
+}
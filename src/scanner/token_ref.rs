@@ -0,0 +1,56 @@
+use std::borrow::Cow;
+
+use rlox_lib::token::{Literal, LiteralType, Token, TokenBuilder, TokenType};
+
+use super::{Number, Scanner, Span};
+
+/// A token borrowing its lexeme from the code string, so that scanning it allocates nothing
+/// unless its literal value needs to (e.g. the value of a string literal).
+/// Produced by `Scanner::token_refs`; `into_token` turns it into an owned `Token` when needed.
+#[derive(Debug, PartialEq)]
+pub struct TokenRef<'a> {
+    pub kind: TokenType,
+    /// Borrowed from the code string, except for identifiers changed by NFC normalization
+    pub lexeme: Cow<'a, str>,
+    pub literal: Option<Literal<LiteralType>>,
+    pub line: usize,
+    pub span: Span,
+    /// Exact value of a number literal, telling integers and floats apart
+    pub number: Option<Number>,
+}
+
+impl<'a> TokenRef<'a> {
+    /// Converts into an owned token, copying the lexeme
+    pub fn into_token(self) -> Token<LiteralType> {
+        TokenBuilder::new()
+            .kind(self.kind)
+            .lexeme(self.lexeme.into_owned())
+            .line(self.line)
+            .literal(self.literal)
+            .build()
+    }
+}
+
+impl<'a> From<TokenRef<'a>> for Token<LiteralType> {
+    fn from(token: TokenRef<'a>) -> Self {
+        token.into_token()
+    }
+}
+
+impl<'a> Scanner<'a> {
+    /// Builds the token scanned since `start`, borrowing its lexeme from the code string
+    pub(crate) fn make_token(
+        &self,
+        kind: TokenType,
+        literal: Option<Literal<LiteralType>>,
+    ) -> TokenRef<'a> {
+        TokenRef {
+            kind,
+            lexeme: Cow::Borrowed(&self.code[self.start.ptr..self.current_ptr]),
+            literal,
+            line: self.start.line,
+            span: self.current_span(),
+            number: None,
+        }
+    }
+}
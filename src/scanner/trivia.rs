@@ -0,0 +1,92 @@
+use crate::constants::NEWLINE;
+
+use super::{Scanner, Span};
+
+/// What a piece of trivia is made of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// A run of spaces, tabs and carriage returns
+    Whitespace,
+    /// A single line break
+    Newline,
+    /// A `//` comment, without the line break ending it
+    LineComment,
+    /// A `/* */` comment
+    BlockComment,
+    /// Source text that could not be scanned into a token, e.g. an unexpected character or an
+    /// unterminated string. The error itself is yielded and recorded as usual.
+    Skipped,
+}
+
+/// Source text between tokens that carries no meaning for the parser.
+/// Only produced in lossless mode, see `LexerConfig::lossless`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+
+impl<'a> Scanner<'a> {
+    /// Turns the code scanned since `start` into trivia of the given kind, to be attached to the
+    /// next token. Adjacent whitespace is merged into a single piece.
+    pub(crate) fn push_trivia(&mut self, kind: TriviaKind) {
+        let span = self.current_span();
+        let text = &self.code[span.start..span.end];
+        if let Some(last) = self.pending_trivia.last_mut() {
+            if kind == TriviaKind::Whitespace && last.kind == TriviaKind::Whitespace {
+                last.text.push_str(text);
+                last.span.end = span.end;
+                last.span.end_line = span.end_line;
+                last.span.end_column = span.end_column;
+                return;
+            }
+        }
+        self.pending_trivia.push(Trivia {
+            kind,
+            text: String::from(text),
+            span,
+        });
+    }
+
+    /// Kind of the trivia scanned since `start`, which was skipped over without producing a token
+    pub(crate) fn trivia_kind(&self) -> TriviaKind {
+        let text = &self.code[self.start.ptr..self.current_ptr];
+        if text.starts_with(NEWLINE) {
+            TriviaKind::Newline
+        } else if text.starts_with("//") {
+            TriviaKind::LineComment
+        } else if text.starts_with("/*") {
+            TriviaKind::BlockComment
+        } else {
+            TriviaKind::Whitespace
+        }
+    }
+
+    /// Scans the trivia following a token on its own line: whitespace, a `//` comment and the
+    /// line break ending the line. Block comments are left as leading trivia of the next token.
+    pub(crate) fn trailing_trivia(&mut self) -> Vec<Trivia> {
+        while let Some(c) = self.peek() {
+            self.start = self.cursor();
+            match c {
+                ' ' | '\r' | '\t' => {
+                    self.advance();
+                    self.push_trivia(TriviaKind::Whitespace);
+                }
+                NEWLINE => {
+                    self.advance();
+                    self.push_trivia(TriviaKind::Newline);
+                    break;
+                }
+                '/' if self.code[self.current_ptr..].starts_with("//") => {
+                    self.seek_until(NEWLINE);
+                    self.check_invisible_characters();
+                    self.collect_comment();
+                    self.push_trivia(TriviaKind::LineComment);
+                }
+                _ => break,
+            }
+        }
+        std::mem::take(&mut self.pending_trivia)
+    }
+}
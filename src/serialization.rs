@@ -0,0 +1,330 @@
+//! Reading and writing token streams as JSON, so that they can be stored, diffed across lexer
+//! versions and fed into other tooling.
+//!
+//! Every token is written as an object:
+//!
+//! ```json
+//! {"kind":"Number","lexeme":"12.5","literal":{"type":"float","value":12.5},"line":2,
+//!  "span":{"start":14,"end":18,"start_line":2,"start_column":9,"end_line":2,"end_column":13}}
+//! ```
+//!
+//! `literal` is `null` for tokens without a literal value. Number tokens also have a `number`
+//! field telling integers from floats, e.g. `"number":{"type":"integer","value":42}`.
+//! Float values JSON numbers cannot hold are written as the strings `"inf"`, `"-inf"` and
+//! `"nan"`. Trivia is not serialized.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use rlox_lib::token::{Literal, LiteralType, TokenBuilder};
+use serde_json::{json, Map, Value};
+
+use crate::constants::TOKEN_TYPES;
+use crate::scanner::{Number, Span, SpannedToken};
+
+/// Layout of a serialized token stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A single JSON array holding every token
+    Json,
+    /// One JSON object per line
+    JsonLines,
+}
+
+/// Errors while reading a serialized token stream back
+#[derive(Debug)]
+pub enum DecodeError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// The JSON is well formed but does not describe a token
+    InvalidToken(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DecodeError::Io(error) => write!(f, "could not read token stream: {}", error),
+            DecodeError::Json(error) => write!(f, "invalid JSON: {}", error),
+            DecodeError::InvalidToken(message) => write!(f, "invalid token: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<io::Error> for DecodeError {
+    fn from(error: io::Error) -> Self {
+        DecodeError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for DecodeError {
+    fn from(error: serde_json::Error) -> Self {
+        DecodeError::Json(error)
+    }
+}
+
+/// Writes the tokens in the given format
+pub fn write_tokens<W: Write>(
+    writer: &mut W,
+    tokens: &[SpannedToken],
+    format: Format,
+) -> io::Result<()> {
+    match format {
+        Format::Json => {
+            let array = Value::Array(tokens.iter().map(token_to_json).collect());
+            serde_json::to_writer_pretty(&mut *writer, &array)?;
+            writeln!(writer)
+        }
+        Format::JsonLines => {
+            for token in tokens {
+                serde_json::to_writer(&mut *writer, &token_to_json(token))?;
+                writeln!(writer)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Reads tokens written by `write_tokens` in the same format
+pub fn read_tokens<R: Read>(reader: R, format: Format) -> Result<Vec<SpannedToken>, DecodeError> {
+    match format {
+        Format::Json => match serde_json::from_reader(reader)? {
+            Value::Array(values) => values.iter().map(token_from_json).collect(),
+            _ => Err(DecodeError::InvalidToken(String::from(
+                "expected an array of tokens",
+            ))),
+        },
+        Format::JsonLines => {
+            let mut tokens = vec![];
+            for line in BufReader::new(reader).lines() {
+                let line = line?;
+                // Blank lines are tolerated, e.g. a trailing one at the end of the file
+                if line.trim().is_empty() {
+                    continue;
+                }
+                tokens.push(token_from_json(&serde_json::from_str(&line)?)?);
+            }
+            Ok(tokens)
+        }
+    }
+}
+
+fn token_to_json(token: &SpannedToken) -> Value {
+    let literal = match &token.literal {
+        Some(literal) => literal_to_json(&literal.kind),
+        None => Value::Null,
+    };
+    let mut value = json!({
+        "kind": format!("{:?}", token.kind),
+        "lexeme": token.lexeme,
+        "literal": literal,
+        "line": token.line,
+        "span": {
+            "start": token.span.start,
+            "end": token.span.end,
+            "start_line": token.span.start_line,
+            "start_column": token.span.start_column,
+            "end_line": token.span.end_line,
+            "end_column": token.span.end_column,
+        },
+    });
+    if let Some(number) = &token.number {
+        value["number"] = match number {
+            Number::Integer(value) => json!({ "type": "integer", "value": value }),
+            Number::Float(value) => json!({ "type": "float", "value": float_to_json(*value) }),
+            Number::Decimal(text) => json!({ "type": "decimal", "value": text }),
+        };
+    }
+    value
+}
+
+fn literal_to_json(literal: &LiteralType) -> Value {
+    match literal {
+        LiteralType::String(value) => json!({ "type": "string", "value": value }),
+        LiteralType::Float(value) => json!({ "type": "float", "value": float_to_json(*value) }),
+        LiteralType::Boolean(value) => json!({ "type": "boolean", "value": value }),
+        LiteralType::Nil => json!({ "type": "nil" }),
+    }
+}
+
+fn token_from_json(value: &Value) -> Result<SpannedToken, DecodeError> {
+    let object = as_object(value, "token")?;
+    let kind_name = as_str(field(object, "kind")?, "kind")?;
+    let kind = TOKEN_TYPES
+        .get(kind_name)
+        .copied()
+        .ok_or_else(|| DecodeError::InvalidToken(format!("unknown token kind {:?}", kind_name)))?;
+    let literal = match field(object, "literal")? {
+        Value::Null => None,
+        literal => Some(Literal {
+            kind: literal_from_json(literal)?,
+        }),
+    };
+    let span = as_object(field(object, "span")?, "span")?;
+    let number = match object.get("number") {
+        Some(number) => Some(number_from_json(number)?),
+        None => None,
+    };
+    Ok(SpannedToken {
+        token: TokenBuilder::new()
+            .kind(kind)
+            .lexeme(String::from(as_str(field(object, "lexeme")?, "lexeme")?))
+            .line(as_usize(field(object, "line")?, "line")?)
+            .literal(literal)
+            .build(),
+        span: Span {
+            start: as_usize(field(span, "start")?, "start")?,
+            end: as_usize(field(span, "end")?, "end")?,
+            start_line: as_usize(field(span, "start_line")?, "start_line")?,
+            start_column: as_usize(field(span, "start_column")?, "start_column")?,
+            end_line: as_usize(field(span, "end_line")?, "end_line")?,
+            end_column: as_usize(field(span, "end_column")?, "end_column")?,
+        },
+        raw_lexeme: None,
+        number,
+        leading_trivia: vec![],
+        trailing_trivia: vec![],
+    })
+}
+
+fn literal_from_json(value: &Value) -> Result<LiteralType, DecodeError> {
+    let object = as_object(value, "literal")?;
+    let value = || field(object, "value");
+    match as_str(field(object, "type")?, "type")? {
+        "string" => Ok(LiteralType::String(String::from(as_str(
+            value()?,
+            "value",
+        )?))),
+        "float" => float_from_json(value()?)
+            .map(LiteralType::Float)
+            .ok_or_else(|| invalid_field("value")),
+        "boolean" => value()?
+            .as_bool()
+            .map(LiteralType::Boolean)
+            .ok_or_else(|| invalid_field("value")),
+        "nil" => Ok(LiteralType::Nil),
+        other => Err(DecodeError::InvalidToken(format!(
+            "unknown literal type {:?}",
+            other
+        ))),
+    }
+}
+
+fn number_from_json(value: &Value) -> Result<Number, DecodeError> {
+    let object = as_object(value, "number")?;
+    let value = field(object, "value")?;
+    match as_str(field(object, "type")?, "type")? {
+        "integer" => value
+            .as_u64()
+            .map(Number::Integer)
+            .ok_or_else(|| invalid_field("value")),
+        "float" => float_from_json(value)
+            .map(Number::Float)
+            .ok_or_else(|| invalid_field("value")),
+        "decimal" => Ok(Number::Decimal(String::from(as_str(value, "value")?))),
+        other => Err(DecodeError::InvalidToken(format!(
+            "unknown number type {:?}",
+            other
+        ))),
+    }
+}
+
+/// JSON numbers are finite, so infinities and NaN are written as strings
+fn float_to_json(value: f64) -> Value {
+    if value.is_finite() {
+        json!(value)
+    } else if value.is_nan() {
+        json!("nan")
+    } else if value > 0.0 {
+        json!("inf")
+    } else {
+        json!("-inf")
+    }
+}
+
+fn float_from_json(value: &Value) -> Option<f64> {
+    match value.as_str() {
+        Some("inf") => Some(f64::INFINITY),
+        Some("-inf") => Some(f64::NEG_INFINITY),
+        Some("nan") => Some(f64::NAN),
+        Some(_) => None,
+        None => value.as_f64(),
+    }
+}
+
+fn field<'v>(object: &'v Map<String, Value>, name: &str) -> Result<&'v Value, DecodeError> {
+    object
+        .get(name)
+        .ok_or_else(|| DecodeError::InvalidToken(format!("missing field {:?}", name)))
+}
+
+fn invalid_field(name: &str) -> DecodeError {
+    DecodeError::InvalidToken(format!("invalid value for field {:?}", name))
+}
+
+fn as_object<'v>(value: &'v Value, name: &str) -> Result<&'v Map<String, Value>, DecodeError> {
+    value.as_object().ok_or_else(|| invalid_field(name))
+}
+
+fn as_str<'v>(value: &'v Value, name: &str) -> Result<&'v str, DecodeError> {
+    value.as_str().ok_or_else(|| invalid_field(name))
+}
+
+fn as_usize(value: &Value, name: &str) -> Result<usize, DecodeError> {
+    value
+        .as_u64()
+        .map(|value| value as usize)
+        .ok_or_else(|| invalid_field(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::{LexerConfig, Scanner};
+
+    #[test]
+    fn test_json_lines_round_trip() {
+        let tokens = Scanner::new("var x = 12.5 + 0x2A;\n").scan_tokens().tokens;
+        let mut buffer = vec![];
+        write_tokens(&mut buffer, &tokens, Format::JsonLines).unwrap();
+        let output = String::from_utf8(buffer.clone()).unwrap();
+        assert_eq!(output.lines().count(), tokens.len());
+
+        let decoded = read_tokens(buffer.as_slice(), Format::JsonLines).unwrap();
+        assert_eq!(decoded.len(), tokens.len());
+        for (decoded, original) in decoded.iter().zip(tokens.iter()) {
+            assert_eq!(decoded.kind, original.kind);
+            assert_eq!(decoded.lexeme, original.lexeme);
+            assert_eq!(decoded.literal, original.literal);
+            assert_eq!(decoded.line, original.line);
+            assert_eq!(decoded.span, original.span);
+            assert_eq!(decoded.number, original.number);
+        }
+    }
+
+    #[test]
+    fn test_non_finite_float_round_trip() {
+        let config = LexerConfig::new().exact_numbers(true);
+        let tokens = Scanner::with_config("1e999", config).scan_tokens().tokens;
+        let mut buffer = vec![];
+        write_tokens(&mut buffer, &tokens, Format::Json).unwrap();
+        let decoded = read_tokens(buffer.as_slice(), Format::Json).unwrap();
+        assert_eq!(decoded[0].literal, tokens[0].literal);
+        assert_eq!(decoded[0].number, tokens[0].number);
+
+        assert_eq!(
+            float_from_json(&float_to_json(f64::NEG_INFINITY)),
+            Some(f64::NEG_INFINITY)
+        );
+        assert!(float_from_json(&float_to_json(f64::NAN)).is_some_and(f64::is_nan));
+    }
+
+    #[test]
+    fn test_read_rejects_unknown_kind() {
+        let input = r#"[{"kind":"Walrus","lexeme":":=","literal":null,"line":1,
+            "span":{"start":0,"end":2,"start_line":1,"start_column":1,"end_line":1,"end_column":3}}]"#;
+        let result = read_tokens(input.as_bytes(), Format::Json);
+        assert!(matches!(result, Err(DecodeError::InvalidToken(_))));
+    }
+}
@@ -1,7 +1,16 @@
+use std::borrow::Cow;
+use std::fs;
+
 use log::debug;
 use rstest::rstest;
 
-use rlox_lexer::{constants::NEWLINE, scanner::Scanner, Literal, LiteralType, Token, TokenType};
+use rlox_lexer::{
+    constants::NEWLINE,
+    scanner::Scanner,
+    serialization::{read_tokens, write_tokens, Format},
+    CommentKind, Dialect, Item, LexError, LexerConfig, Literal, LiteralType, Number, Severity,
+    Span, SpannedToken, TokenRef, TokenType, Trivia, TriviaKind,
+};
 
 #[ctor::ctor]
 fn init() {
@@ -13,7 +22,7 @@ fn test_scan_token_for_individual_tokens() {
     let scanner = Scanner::new("{)");
     let tokens = scanner.scan_tokens().tokens;
     debug!("Tokens: {:?}", tokens);
-    assert!(tokens.first().is_some());
+    assert!(!tokens.is_empty());
     assert_eq!(tokens.first().unwrap().lexeme, String::from("{"));
     assert_eq!(tokens.get(1).unwrap().lexeme, String::from(")"));
 }
@@ -45,6 +54,51 @@ fn test_scan_token_for_comment() {
     assert!(tokens.is_empty())
 }
 
+#[rstest]
+#[case("/* comment */ +", false, vec![TokenType::Plus])]
+#[case("- /* multi\nline\n*/ +", false, vec![TokenType::Minus, TokenType::Plus])]
+#[case("/**/ /***/ +", false, vec![TokenType::Plus])]
+#[case("/* a /* b */ + */", false, vec![TokenType::Plus, TokenType::Star, TokenType::Slash])]
+#[case("/* a /* b */ + */ -", true, vec![TokenType::Minus])]
+fn test_block_comments(
+    #[case] input: &str,
+    #[case] nested: bool,
+    #[case] expected: Vec<TokenType>,
+) {
+    let config = LexerConfig::new().nested_comments(nested);
+    let scanner = Scanner::with_config(input, config).scan_tokens();
+    debug!("Tokens: {:?}", scanner.tokens);
+    assert!(scanner.diagnostics.is_empty());
+    let kinds: Vec<TokenType> = scanner.tokens.iter().map(|token| token.kind).collect();
+    assert_eq!(kinds, expected);
+}
+
+#[test]
+fn test_block_comment_line_tracking() {
+    let string = String::from("/* one\ntwo\n */ var");
+    let tokens = Scanner::new(&string).scan_tokens().tokens;
+    assert_eq!(tokens.first().unwrap().kind, TokenType::Var);
+    assert_eq!(tokens.first().unwrap().line, 3);
+    assert_eq!(tokens.first().unwrap().span.start_column, 5);
+}
+
+#[rstest]
+#[case("var x;\n  /* never closed\n", false)]
+#[case("var x;\n  /* /* */ never closed\n", true)]
+fn test_unterminated_block_comment(#[case] input: &str, #[case] nested: bool) {
+    let config = LexerConfig::new().nested_comments(nested);
+    let scanner = Scanner::with_config(input, config).scan_tokens();
+    debug!("Diagnostics: {:?}", scanner.diagnostics);
+    assert_eq!(scanner.tokens.len(), 3);
+    assert_eq!(scanner.diagnostics.len(), 1);
+    let diagnostic = scanner.diagnostics.first().unwrap();
+    assert_eq!(diagnostic.message, "unterminated block comment");
+    // Points at the opening delimiter
+    assert_eq!(&input[diagnostic.span.start..diagnostic.span.end], "/*");
+    assert_eq!(diagnostic.span.start_line, 2);
+    assert_eq!(diagnostic.span.start_column, 3);
+}
+
 #[test]
 fn test_scan_token_for_slash() {
     let mut string = String::from("/!");
@@ -83,7 +137,31 @@ fn test_string_tokens() {
     let tokens = scanner.scan_tokens().tokens;
     debug!("Tokens: {:?}", tokens);
     assert_eq!(tokens.first().unwrap().kind, TokenType::String);
-    assert_eq!(tokens.first().unwrap().lexeme, " Hello World!");
+    assert_eq!(tokens.first().unwrap().lexeme, "\" Hello World!\"");
+    assert_eq!(
+        tokens.first().unwrap().literal.as_ref().unwrap(),
+        &Literal {
+            kind: LiteralType::String(String::from(" Hello World!"))
+        }
+    );
+}
+
+#[rstest]
+#[case("true", Some(LiteralType::Boolean(true)))]
+#[case("false", Some(LiteralType::Boolean(false)))]
+#[case("nil", Some(LiteralType::Nil))]
+#[case("truth", None)]
+#[case("and", None)]
+fn test_keyword_literals(#[case] input: &str, #[case] expected: Option<LiteralType>) {
+    let mut string = String::from(input);
+    string.push(NEWLINE);
+    let tokens = Scanner::new(&string).scan_tokens().tokens;
+    debug!("Tokens: {:?}", tokens);
+    assert_eq!(tokens.first().unwrap().lexeme, input);
+    assert_eq!(
+        tokens.first().unwrap().literal,
+        expected.map(|kind| Literal { kind })
+    );
 }
 
 // #[ignore]
@@ -103,6 +181,320 @@ fn test_numbers() {
     );
 }
 
+#[rstest]
+#[case("1e10", 1e10)]
+#[case("6.02E23", 6.02e23)]
+#[case("1.5e-3", 1.5e-3)]
+#[case("2E+2", 200.0)]
+#[case("0e0", 0.0)]
+fn test_exponent_numbers(#[case] input: &str, #[case] expected: f64) {
+    let scanner = Scanner::new(input).scan_tokens();
+    debug!("Tokens: {:?}", scanner.tokens);
+    assert!(scanner.diagnostics.is_empty());
+    assert_eq!(scanner.tokens.len(), 1);
+    let token = scanner.tokens.first().unwrap();
+    assert_eq!(token.kind, TokenType::Number);
+    assert_eq!(token.lexeme, input);
+    assert_eq!(
+        token.literal,
+        Some(Literal {
+            kind: LiteralType::Float(expected)
+        })
+    );
+}
+
+#[rstest]
+#[case("1e", "1e")]
+#[case("1e+", "1e+")]
+#[case("2.5E- 3", "2.5E-")]
+#[case("7ex", "7e")]
+fn test_missing_exponent_digits(#[case] input: &str, #[case] lexeme: &str) {
+    let mut scanner = Scanner::new(input);
+    let error = scanner.next().unwrap().unwrap_err();
+    assert_eq!(
+        error,
+        LexError::MissingExponentDigits {
+            lexeme: String::from(lexeme),
+            span: Span {
+                start: 0,
+                end: lexeme.len(),
+                start_line: 1,
+                start_column: 1,
+                end_line: 1,
+                end_column: lexeme.len() + 1,
+            },
+        }
+    );
+    assert!(error.help().is_some());
+}
+
+#[rstest]
+#[case("0x1F", 31.0)]
+#[case("0xff", 255.0)]
+#[case("0o17", 15.0)]
+#[case("0b1010", 10.0)]
+#[case("0x0", 0.0)]
+fn test_radix_numbers(#[case] input: &str, #[case] expected: f64) {
+    let scanner = Scanner::new(input).scan_tokens();
+    debug!("Tokens: {:?}", scanner.tokens);
+    assert!(scanner.diagnostics.is_empty());
+    assert_eq!(scanner.tokens.len(), 1);
+    let token = scanner.tokens.first().unwrap();
+    assert_eq!(token.lexeme, input);
+    assert_eq!(
+        token.literal,
+        Some(Literal {
+            kind: LiteralType::Float(expected)
+        })
+    );
+}
+
+#[rstest]
+#[case("0b102", '2', 2, 4)]
+#[case("0o78", '8', 8, 3)]
+#[case("0x1G", 'G', 16, 3)]
+#[case("0b1a0", 'a', 2, 3)]
+fn test_invalid_radix_digits(
+    #[case] input: &str,
+    #[case] digit: char,
+    #[case] radix: u32,
+    #[case] start: usize,
+) {
+    let mut scanner = Scanner::new(input);
+    let error = scanner.next().unwrap().unwrap_err();
+    assert_eq!(
+        error,
+        LexError::InvalidDigit {
+            digit,
+            radix,
+            span: Span {
+                start,
+                end: start + 1,
+                start_line: 1,
+                start_column: start + 1,
+                end_line: 1,
+                end_column: start + 2,
+            },
+        }
+    );
+    // The whole literal is consumed
+    assert!(scanner.next().is_none());
+}
+
+#[test]
+fn test_missing_radix_digits() {
+    let mut scanner = Scanner::new("0x;");
+    let error = scanner.next().unwrap().unwrap_err();
+    assert!(matches!(error, LexError::MissingDigits { ref lexeme, .. } if lexeme == "0x"));
+    assert_eq!(scanner.next().unwrap().unwrap().kind, TokenType::SemiColon);
+}
+
+#[rstest]
+#[case("3", Number::Integer(3))]
+#[case("10000000", Number::Integer(10_000_000))]
+#[case("1_000_000", Number::Integer(1_000_000))]
+#[case("0xFF_FF", Number::Integer(0xffff))]
+#[case("0b1010_1010", Number::Integer(0b1010_1010))]
+#[case("3.0", Number::Float(3.0))]
+#[case("1_000.000_1", Number::Float(1000.0001))]
+#[case("1e1_0", Number::Float(1e10))]
+#[case("18446744073709551616", Number::Float(18446744073709551616.0))]
+fn test_integer_and_float_literals(#[case] input: &str, #[case] expected: Number) {
+    let scanner = Scanner::new(input).scan_tokens();
+    debug!("Tokens: {:?}", scanner.tokens);
+    assert!(scanner.diagnostics.is_empty());
+    assert_eq!(scanner.tokens.len(), 1);
+    let token = scanner.tokens.first().unwrap();
+    assert_eq!(token.lexeme, input);
+    assert_eq!(
+        token.literal,
+        Some(Literal {
+            kind: LiteralType::Float(expected.as_f64())
+        })
+    );
+    assert_eq!(token.number, Some(expected));
+}
+
+#[rstest]
+#[case(
+    "9007199254740993",
+    "integer literal `9007199254740993` is rounded to 9007199254740992 as a float"
+)]
+#[case(
+    "0x20000000000001",
+    "integer literal `0x20000000000001` is rounded to 9007199254740992 as a float"
+)]
+#[case(
+    "3.14159265358979323846",
+    "number literal `3.14159265358979323846` is rounded to 3.141592653589793 as a float"
+)]
+#[case(
+    "9007199254740993.0",
+    "number literal `9007199254740993.0` is rounded to 9007199254740992 as a float"
+)]
+#[case("1e-400", "number literal `1e-400` rounds to zero")]
+fn test_lossy_number_literals(#[case] input: &str, #[case] message: &str) {
+    let scanner = Scanner::new(input).scan_tokens();
+    debug!("Diagnostics: {:?}", scanner.diagnostics);
+    assert_eq!(scanner.diagnostics.len(), 1);
+    let diagnostic = scanner.diagnostics.first().unwrap();
+    assert_eq!(diagnostic.severity, Severity::Warning);
+    assert_eq!(diagnostic.message, message);
+    assert_eq!(diagnostic.span.len(), input.len());
+    assert_eq!(scanner.tokens.len(), 1);
+}
+
+#[rstest]
+#[case("0.1")]
+#[case("126.32")]
+#[case("100.000")]
+#[case("0.015e2")]
+#[case("1.7976931348623157e308")]
+#[case("5e-324")]
+fn test_float_literals_read_back_exactly(#[case] input: &str) {
+    let scanner = Scanner::new(input).scan_tokens();
+    debug!("Diagnostics: {:?}", scanner.diagnostics);
+    assert!(scanner.diagnostics.is_empty());
+    assert_eq!(scanner.tokens.len(), 1);
+}
+
+#[rstest]
+#[case("1e400")]
+#[case("179_769_313_486_231_570_814_527e300")]
+#[case("0x1_0000_0000_0000_0000")]
+fn test_number_overflow(#[case] input: &str) {
+    let mut scanner = Scanner::new(input);
+    let error = scanner.next().unwrap().unwrap_err();
+    debug!("Error: {}", error);
+    assert!(matches!(error, LexError::NumberOverflow { ref lexeme, .. } if lexeme == input));
+    assert!(scanner.next().is_none());
+}
+
+#[rstest]
+#[case("9007199254740993", "9007199254740993")]
+#[case("1_000.000_000_000_000_000_1", "1000.0000000000000001")]
+fn test_exact_numbers(#[case] input: &str, #[case] text: &str) {
+    let config = LexerConfig::new().exact_numbers(true);
+    let scanner = Scanner::with_config(input, config).scan_tokens();
+    assert!(scanner.diagnostics.is_empty());
+    let token = scanner.tokens.first().unwrap();
+    assert_eq!(token.number, Some(Number::Decimal(String::from(text))));
+}
+
+#[test]
+fn test_exact_number_too_large_for_a_float() {
+    let config = LexerConfig::new().exact_numbers(true);
+    let scanner = Scanner::with_config("1e999", config).scan_tokens();
+    debug!("Diagnostics: {:?}", scanner.diagnostics);
+    assert_eq!(scanner.diagnostics.len(), 1);
+    let diagnostic = scanner.diagnostics.first().unwrap();
+    assert_eq!(diagnostic.severity, Severity::Warning);
+    assert_eq!(
+        diagnostic.message,
+        "number literal `1e999` is too large for a float"
+    );
+    let token = scanner.tokens.first().unwrap();
+    assert_eq!(token.number, Some(Number::Decimal(String::from("1e999"))));
+    assert_eq!(
+        token.literal,
+        Some(Literal {
+            kind: LiteralType::Float(f64::INFINITY)
+        })
+    );
+}
+
+#[rstest]
+#[case("1_", "trailing digit separator", 1, 2)]
+#[case("1__000", "doubled digit separator", 1, 3)]
+#[case("1_.5", "trailing digit separator", 1, 2)]
+#[case("1.5_e3", "trailing digit separator", 3, 4)]
+#[case("1e_5", "leading digit separator", 2, 3)]
+#[case("0x_1F", "leading digit separator", 2, 3)]
+#[case("0b1__", "trailing digit separator", 3, 5)]
+fn test_misplaced_digit_separators(
+    #[case] input: &str,
+    #[case] message: &str,
+    #[case] start: usize,
+    #[case] end: usize,
+) {
+    let scanner = Scanner::new(input).scan_tokens();
+    debug!("Diagnostics: {:?}", scanner.diagnostics);
+    assert_eq!(scanner.diagnostics.len(), 1);
+    let diagnostic = scanner.diagnostics.first().unwrap();
+    assert!(diagnostic.is_error());
+    assert_eq!(diagnostic.message, message);
+    assert_eq!((diagnostic.span.start, diagnostic.span.end), (start, end));
+    // The number is still scanned, separators ignored
+    assert_eq!(scanner.tokens.first().unwrap().kind, TokenType::Number);
+}
+
+#[rstest]
+#[case("1.5", false, &[(TokenType::Number, "1.5")], 0)]
+#[case("123.", false, &[(TokenType::Number, "123"), (TokenType::Dot, ".")], 0)]
+#[case("123.", true, &[(TokenType::Number, "123.")], 1)]
+#[case(".5", false, &[(TokenType::Dot, "."), (TokenType::Number, "5")], 0)]
+#[case(".5", true, &[(TokenType::Number, ".5")], 1)]
+#[case("-.5", true, &[(TokenType::Minus, "-"), (TokenType::Number, ".5")], 1)]
+#[case(".5e2", true, &[(TokenType::Number, ".5e2")], 1)]
+#[case(
+    "3.abs",
+    false,
+    &[(TokenType::Number, "3"), (TokenType::Dot, "."), (TokenType::Identifier, "abs")],
+    0
+)]
+#[case(
+    "3.abs",
+    true,
+    &[(TokenType::Number, "3"), (TokenType::Dot, "."), (TokenType::Identifier, "abs")],
+    0
+)]
+#[case(
+    "1.e5",
+    false,
+    &[(TokenType::Number, "1"), (TokenType::Dot, "."), (TokenType::Identifier, "e5")],
+    0
+)]
+#[case(
+    "1..2",
+    false,
+    &[(TokenType::Number, "1"), (TokenType::Dot, "."), (TokenType::Dot, "."), (TokenType::Number, "2")],
+    0
+)]
+#[case("x = 5.;", true, &[(TokenType::Identifier, "x"), (TokenType::Equal, "="), (TokenType::Number, "5."), (TokenType::SemiColon, ";")], 1)]
+#[case("(.5)", false, &[(TokenType::LeftParen, "("), (TokenType::Dot, "."), (TokenType::Number, "5"), (TokenType::RightParen, ")")], 0)]
+fn test_number_dots(
+    #[case] input: &str,
+    #[case] lenient: bool,
+    #[case] expected: &[(TokenType, &str)],
+    #[case] warnings: usize,
+) {
+    let config = LexerConfig::new().lenient_dots(lenient);
+    let scanner = Scanner::with_config(input, config).scan_tokens();
+    debug!("Tokens: {:?}", scanner.tokens);
+    let tokens: Vec<(TokenType, &str)> = scanner
+        .tokens
+        .iter()
+        .map(|token| (token.kind, token.lexeme.as_str()))
+        .collect();
+    assert_eq!(tokens, expected);
+    assert_eq!(scanner.diagnostics.len(), warnings);
+    assert!(scanner
+        .diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.severity == Severity::Warning));
+}
+
+#[rstest]
+#[case(".5", 0.5, "write it as `0.5`")]
+#[case("5.", 5.0, "write it as `5.0`")]
+fn test_lenient_dot_values(#[case] input: &str, #[case] expected: f64, #[case] help: &str) {
+    let config = LexerConfig::new().lenient_dots(true);
+    let scanner = Scanner::with_config(input, config).scan_tokens();
+    let token = scanner.tokens.first().unwrap();
+    assert_eq!(token.number, Some(Number::Float(expected)));
+    assert_eq!(scanner.diagnostics.first().unwrap().help, vec![help]);
+}
+
 #[test]
 fn test_identifier() {
     let mut string = String::from("for");
@@ -114,6 +506,689 @@ fn test_identifier() {
     assert_eq!(tokens.first().unwrap().lexeme, "for");
 }
 
+#[test]
+fn test_scanner_as_iterator() {
+    let mut string = String::from("var x = 1; var y = 2;");
+    string.push(NEWLINE);
+    let scanner = Scanner::new(&string);
+    // Only pull the first statement
+    let tokens: Vec<SpannedToken> = scanner
+        .map(|result| result.unwrap())
+        .take_while(|token| token.kind != TokenType::SemiColon)
+        .collect();
+    debug!("Tokens: {:?}", tokens);
+    assert_eq!(tokens.len(), 4);
+    assert_eq!(tokens.first().unwrap().kind, TokenType::Var);
+    assert_eq!(tokens.last().unwrap().kind, TokenType::Number);
+}
+
+#[test]
+fn test_scanner_iterator_yields_errors() {
+    let mut string = String::from("( # )");
+    string.push(NEWLINE);
+    let scanner = Scanner::new(&string);
+    let results: Vec<_> = scanner.collect();
+    assert_eq!(results.len(), 3);
+    assert!(results.first().unwrap().is_ok());
+    assert!(matches!(
+        results.get(1).unwrap(),
+        Err(LexError::UnexpectedCharacter { character: '#', .. })
+    ));
+    assert!(results.get(2).unwrap().is_ok());
+}
+
+#[test]
+fn test_diagnostics_are_returned_with_tokens() {
+    let string = String::from("var x = 1;\nx = \"oops");
+    let scanner = Scanner::new(&string).scan_tokens();
+    debug!("Diagnostics: {:?}", scanner.diagnostics);
+    assert_eq!(scanner.tokens.len(), 7);
+    assert_eq!(scanner.diagnostics.len(), 1);
+    let diagnostic = scanner.diagnostics.first().unwrap();
+    assert_eq!(diagnostic.severity, Severity::Error);
+    assert_eq!(diagnostic.message, "unterminated string");
+    assert_eq!(diagnostic.span.start_line, 2);
+    assert_eq!(diagnostic.span.start_column, 5);
+}
+
+#[test]
+fn test_multi_line_string() {
+    let string = String::from("var s = \"one\ntwo\n\";\nvar t;");
+    let tokens = Scanner::new(&string).scan_tokens().tokens;
+    debug!("Tokens: {:?}", tokens);
+    let string_token = tokens.get(3).unwrap();
+    assert_eq!(string_token.kind, TokenType::String);
+    assert_eq!(string_token.line, 1);
+    assert_eq!(
+        string_token.literal,
+        Some(Literal {
+            kind: LiteralType::String(String::from("one\ntwo\n"))
+        })
+    );
+    assert_eq!(string_token.span.start_line, 1);
+    assert_eq!(string_token.span.end_line, 3);
+    assert_eq!(string_token.span.end_column, 2);
+    // Lines keep counting correctly after the string
+    assert_eq!(tokens.get(4).unwrap().line, 3);
+    assert_eq!(tokens.get(5).unwrap().kind, TokenType::Var);
+    assert_eq!(tokens.get(5).unwrap().line, 4);
+}
+
+#[rstest]
+#[case(r#""a\"b""#, "a\"b")]
+#[case(r#""back\\slash""#, "back\\slash")]
+#[case(r#""line\nbreak""#, "line\nbreak")]
+#[case(r#""\ttab\rreturn\0""#, "\ttab\rreturn\0")]
+#[case(r#""\u{e9}t\u{1F600}""#, "ét😀")]
+fn test_string_escapes(#[case] input: &str, #[case] expected: &str) {
+    let config = LexerConfig::new().escapes(true);
+    let scanner = Scanner::with_config(input, config).scan_tokens();
+    debug!("Tokens: {:?}", scanner.tokens);
+    assert!(scanner.diagnostics.is_empty());
+    let token = scanner.tokens.first().unwrap();
+    assert_eq!(token.kind, TokenType::String);
+    // The lexeme keeps the source text as written
+    assert_eq!(token.lexeme, input);
+    assert_eq!(
+        token.literal,
+        Some(Literal {
+            kind: LiteralType::String(String::from(expected))
+        })
+    );
+}
+
+#[rstest]
+#[case(r#""a\qb""#, r"\q", "unknown escape sequence `\\q`")]
+#[case(
+    r#""\u{110000}""#,
+    r"\u{110000}",
+    "invalid unicode escape `\\u{110000}`"
+)]
+#[case(r#""\u{12""#, r"\u{12", "invalid unicode escape `\\u{12`")]
+#[case(r#""\u41""#, r"\u", "invalid unicode escape `\\u`")]
+#[case("\"a\\\nb\"", r"\", "incomplete escape sequence")]
+fn test_invalid_string_escapes(#[case] input: &str, #[case] sequence: &str, #[case] message: &str) {
+    let config = LexerConfig::new().escapes(true);
+    let scanner = Scanner::with_config(input, config).scan_tokens();
+    debug!("Diagnostics: {:?}", scanner.diagnostics);
+    // The string token is still produced
+    assert_eq!(scanner.tokens.first().unwrap().kind, TokenType::String);
+    assert_eq!(scanner.diagnostics.len(), 1);
+    let diagnostic = scanner.diagnostics.first().unwrap();
+    assert_eq!(diagnostic.message, message);
+    assert_eq!(&input[diagnostic.span.start..diagnostic.span.end], sequence);
+}
+
+#[test]
+fn test_string_escapes_are_opt_in() {
+    let string = String::from(r#""a\n""#);
+    let scanner = Scanner::new(&string).scan_tokens();
+    assert!(scanner.diagnostics.is_empty());
+    assert_eq!(
+        scanner.tokens.first().unwrap().literal,
+        Some(Literal {
+            kind: LiteralType::String(String::from(r"a\n"))
+        })
+    );
+}
+
+#[test]
+fn test_unterminated_string_drops_escape_diagnostics() {
+    let string = String::from("x = \"oops\nprint \\q;");
+    let config = LexerConfig::new().escapes(true);
+    let scanner = Scanner::with_config(&string, config).scan_tokens();
+    debug!("Diagnostics: {:?}", scanner.diagnostics);
+    // The escape is scanned again as code on the next line, so it is only reported once
+    let messages: Vec<&str> = scanner
+        .diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect();
+    assert_eq!(messages.len(), 2);
+    assert!(messages[0].contains("unterminated string"));
+    assert!(messages[1].contains("unexpected character"));
+    assert_eq!(scanner.diagnostics[1].span.start, 16);
+}
+
+#[test]
+fn test_scanning_continues_after_unterminated_string() {
+    let string = String::from("x = \"oops;\nvar y = 1;\ny = 2;");
+    let scanner = Scanner::new(&string);
+    let results: Vec<_> = scanner.collect();
+    debug!("Results: {:?}", results);
+    let kinds: Vec<Option<TokenType>> = results
+        .iter()
+        .map(|result| result.as_ref().ok().map(|token| token.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            Some(TokenType::Identifier),
+            Some(TokenType::Equal),
+            None,
+            Some(TokenType::Var),
+            Some(TokenType::Identifier),
+            Some(TokenType::Equal),
+            Some(TokenType::Number),
+            Some(TokenType::SemiColon),
+            Some(TokenType::Identifier),
+            Some(TokenType::Equal),
+            Some(TokenType::Number),
+            Some(TokenType::SemiColon),
+        ]
+    );
+    // The error covers the bad region up to the end of its line
+    let span = results.get(2).unwrap().as_ref().unwrap_err().span();
+    assert_eq!(&string[span.start..span.end], "\"oops;");
+    assert_eq!(span.start_line, 1);
+    assert_eq!(results.get(3).unwrap().as_ref().unwrap().line, 2);
+}
+
+#[test]
+fn test_token_spans() {
+    let string = String::from("var x =\n  \"é\";");
+    let scanner = Scanner::new(&string);
+    let tokens: Vec<SpannedToken> = scanner.scan_tokens().tokens;
+    debug!("Tokens: {:?}", tokens);
+    assert_eq!(
+        tokens.get(1).unwrap().span,
+        Span {
+            start: 4,
+            end: 5,
+            start_line: 1,
+            start_column: 5,
+            end_line: 1,
+            end_column: 6,
+        }
+    );
+    let string_token = tokens.get(3).unwrap();
+    assert_eq!(string_token.kind, TokenType::String);
+    assert_eq!(
+        &string[string_token.span.start..string_token.span.end],
+        "\"é\""
+    );
+    assert_eq!(string_token.span.start_line, 2);
+    assert_eq!(string_token.span.start_column, 3);
+    assert_eq!(string_token.span.end_column, 6);
+    // Column is counted in characters while the offset is counted in bytes
+    let semicolon = tokens.get(4).unwrap();
+    assert_eq!(semicolon.span.start, 14);
+    assert_eq!(semicolon.span.start_column, 6);
+}
+
+#[rstest]
+#[case("\"日本語 🦀\" x")]
+#[case("// ünïcödé comment\n x")]
+#[case("/* 多\n行 */ x")]
+#[case("\"a\\u{e9}\\n é\" \t x")]
+#[case("\"line\nnext é\" x")]
+fn test_spans_after_non_ascii_text(#[case] input: &str) {
+    let tokens = Scanner::new(input).scan_tokens().tokens;
+    debug!("Tokens: {:?}", tokens);
+    let last = tokens.last().unwrap();
+    assert_eq!(last.lexeme, "x");
+    // Offsets are counted in bytes and columns in characters
+    let before = &input[..input.len() - 1];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    assert_eq!(last.span.start, before.len());
+    assert_eq!(last.span.start_line, before.matches('\n').count() + 1);
+    assert_eq!(
+        last.span.start_column,
+        before[line_start..].chars().count() + 1
+    );
+}
+
+#[test]
+fn test_json_round_trip() {
+    let string = fs::read_to_string("tests/fixtures/program_1.lox").unwrap();
+    let tokens = Scanner::new(&string).scan_tokens().tokens;
+    let mut buffer = vec![];
+    write_tokens(&mut buffer, &tokens, Format::Json).unwrap();
+    let decoded = read_tokens(buffer.as_slice(), Format::Json).unwrap();
+    debug!("Decoded: {:?}", decoded);
+    assert_eq!(decoded.len(), 28);
+    assert_eq!(decoded.first().unwrap().kind, TokenType::Fun);
+    assert_eq!(decoded.first().unwrap().span, tokens.first().unwrap().span);
+    assert_eq!(
+        decoded.get(8).unwrap().literal,
+        Some(Literal {
+            kind: LiteralType::Float(1.0)
+        })
+    );
+}
+
+#[rstest]
+#[case("var x = 1;\n")]
+#[case("  fun f() {\n\treturn \"é\"; // done\r\n}\n\n")]
+#[case("/* lead */ a /* mid */ b // tail")]
+#[case("x = \"multi\nline\";\n  ")]
+#[case("a # b @\n\"unterminated\nc")]
+#[case("/* never closed\n a")]
+#[case("")]
+fn test_lossless_round_trip(#[case] input: &str) {
+    let config = LexerConfig::new().lossless(true);
+    let tokens = Scanner::with_config(input, config).scan_tokens().tokens;
+    debug!("Tokens: {:?}", tokens);
+    let text: String = tokens.iter().map(SpannedToken::full_text).collect();
+    assert_eq!(text, input);
+    assert_eq!(tokens.last().unwrap().kind, TokenType::Eof);
+}
+
+#[test]
+fn test_lossless_trivia_attachment() {
+    let code = "// header\nvar x; // note\n/* doc */ print";
+    let config = LexerConfig::new().lossless(true);
+    let tokens = Scanner::with_config(code, config).scan_tokens().tokens;
+    let kinds = |trivia: &[Trivia]| trivia.iter().map(|t| t.kind).collect::<Vec<_>>();
+
+    let var = tokens.first().unwrap();
+    assert_eq!(
+        kinds(&var.leading_trivia),
+        vec![TriviaKind::LineComment, TriviaKind::Newline]
+    );
+    assert_eq!(kinds(&var.trailing_trivia), vec![TriviaKind::Whitespace]);
+
+    let semicolon = tokens.get(2).unwrap();
+    assert_eq!(
+        kinds(&semicolon.trailing_trivia),
+        vec![
+            TriviaKind::Whitespace,
+            TriviaKind::LineComment,
+            TriviaKind::Newline
+        ]
+    );
+    assert_eq!(semicolon.trailing_trivia.get(1).unwrap().text, "// note");
+
+    let last = tokens.get(3).unwrap();
+    assert_eq!(
+        kinds(&last.leading_trivia),
+        vec![TriviaKind::BlockComment, TriviaKind::Whitespace]
+    );
+    assert_eq!(last.leading_trivia.first().unwrap().span.start_line, 3);
+}
+
+#[test]
+fn test_trivia_is_dropped_by_default() {
+    let tokens = Scanner::new("a // comment\n b").scan_tokens().tokens;
+    assert_eq!(tokens.len(), 2);
+    assert!(tokens
+        .iter()
+        .all(|token| token.leading_trivia.is_empty() && token.trailing_trivia.is_empty()));
+}
+
+#[rstest]
+#[case("/// Adds\nfun add() {}", CommentKind::Doc)]
+#[case("/// A point\n\n  class Point {}", CommentKind::Doc)]
+#[case("/// not documenting\nvar x;", CommentKind::Line)]
+#[case("/// interrupted\n// plain\nfun f() {}", CommentKind::Line)]
+#[case("//// banner\nfun f() {}", CommentKind::Line)]
+#[case("// TODO: rename\nfun f() {}", CommentKind::Line)]
+#[case("/* block */ fun f() {}", CommentKind::Block)]
+#[case("/// trailing", CommentKind::Line)]
+fn test_comment_kinds(#[case] input: &str, #[case] expected: CommentKind) {
+    let config = LexerConfig::new().comments(true);
+    let comments = Scanner::with_config(input, config).scan_tokens().comments;
+    debug!("Comments: {:?}", comments);
+    assert_eq!(comments.first().unwrap().kind, expected);
+}
+
+#[rstest]
+#[case(false)]
+#[case(true)]
+fn test_comments_with_spans(#[case] lossless: bool) {
+    let code = "var x; // TODO: rename\n/// Greets\n/// politely\nfun greet() {} /* end */";
+    let config = LexerConfig::new().comments(true).lossless(lossless);
+    let scanner = Scanner::with_config(code, config).scan_tokens();
+    let comments = scanner.comments;
+    debug!("Comments: {:?}", comments);
+    assert_eq!(comments.len(), 4);
+
+    let todo = comments.first().unwrap();
+    assert_eq!(todo.kind, CommentKind::Line);
+    assert_eq!(todo.text, "// TODO: rename");
+    assert_eq!(todo.content(), " TODO: rename");
+    assert_eq!(&code[todo.span.start..todo.span.end], todo.text);
+    assert_eq!((todo.span.start_line, todo.span.start_column), (1, 8));
+
+    let docs: Vec<&str> = comments
+        .iter()
+        .filter(|comment| comment.kind == CommentKind::Doc)
+        .map(|comment| comment.content())
+        .collect();
+    assert_eq!(docs, vec![" Greets", " politely"]);
+
+    let block = comments.last().unwrap();
+    assert_eq!(block.kind, CommentKind::Block);
+    assert_eq!(block.content(), " end ");
+    assert_eq!(block.span.start_line, 4);
+}
+
+#[rstest]
+#[case(false)]
+#[case(true)]
+fn test_items_in_source_order(#[case] lossless: bool) {
+    let code = "var x; // TODO\n/// Greets\nfun greet() {} /* end */ #";
+    let config = LexerConfig::new().comments(true).lossless(lossless);
+    let mut scanner = Scanner::with_config(code, config);
+    let items: Vec<String> = scanner
+        .items()
+        .map(|item| match item {
+            Ok(Item::Token(token)) => token.lexeme.clone(),
+            Ok(Item::Comment(comment)) => format!("{:?} {}", comment.kind, comment.text),
+            Err(error) => error.to_string(),
+        })
+        .collect();
+    debug!("Items: {:?}", items);
+    let mut expected = vec![
+        "var",
+        "x",
+        ";",
+        "Line // TODO",
+        "Doc /// Greets",
+        "fun",
+        "greet",
+        "(",
+        ")",
+        "{",
+        "}",
+        "Block /* end */",
+        "unexpected character '#' at line 3, column 26",
+    ];
+    if lossless {
+        // The `Eof` token ending a lossless stream
+        expected.push("");
+    }
+    assert_eq!(items, expected);
+    assert_eq!(scanner.comments.len(), 3);
+}
+
+#[test]
+fn test_comments_are_not_collected_by_default() {
+    let scanner = Scanner::new("// comment\n/* block */").scan_tokens();
+    assert!(scanner.comments.is_empty());
+}
+
+#[rstest]
+#[case("or", TokenType::Or)]
+#[case("print", TokenType::Print)]
+#[case("fun", TokenType::Fun)]
+#[case("fn", TokenType::Identifier)]
+fn test_standard_keywords(#[case] input: &str, #[case] expected: TokenType) {
+    let tokens = Scanner::new(input).scan_tokens().tokens;
+    assert_eq!(tokens.first().unwrap().kind, expected);
+}
+
+#[test]
+fn test_custom_dialect() {
+    let dialect = Dialect::lox()
+        .without_keyword("fun")
+        .with_keyword("fn", TokenType::Fun)
+        .unwrap();
+    let config = LexerConfig::new().dialect(dialect);
+    let tokens = Scanner::with_config("fn fun", config).scan_tokens().tokens;
+    debug!("Tokens: {:?}", tokens);
+    assert_eq!(tokens.first().unwrap().kind, TokenType::Fun);
+    assert_eq!(tokens.get(1).unwrap().kind, TokenType::Identifier);
+}
+
+#[rstest]
+#[case("foo_bar", false, &["foo_bar"])]
+#[case("x1 y22z", false, &["x1", "y22z"])]
+#[case("_private", false, &["_private"])]
+#[case("_", false, &["_"])]
+#[case("__init__", false, &["__init__"])]
+#[case("1x", false, &["1", "x"])]
+#[case("for_each", false, &["for_each"])]
+#[case("café", true, &["café"])]
+#[case("π2 λ_μ", true, &["π2", "λ_μ"])]
+#[case("数据1", true, &["数据1"])]
+fn test_identifiers(#[case] input: &str, #[case] unicode: bool, #[case] expected: &[&str]) {
+    let config = LexerConfig::new().unicode_identifiers(unicode);
+    let scanner = Scanner::with_config(input, config).scan_tokens();
+    debug!("Tokens: {:?}", scanner.tokens);
+    assert!(scanner.diagnostics.is_empty());
+    let lexemes: Vec<&str> = scanner
+        .tokens
+        .iter()
+        .map(|token| token.lexeme.as_str())
+        .collect();
+    assert_eq!(lexemes, expected);
+    assert!(scanner
+        .tokens
+        .iter()
+        .filter(|token| token.lexeme.starts_with(|c: char| !c.is_ascii_digit()))
+        .all(|token| token.kind == TokenType::Identifier));
+}
+
+#[rstest]
+#[case("foo", 1)]
+#[case("var x = foo", 4)]
+#[case("print true", 2)]
+fn test_identifier_at_end_of_code(#[case] input: &str, #[case] count: usize) {
+    let tokens = Scanner::new(input).scan_tokens().tokens;
+    debug!("Tokens: {:?}", tokens);
+    assert_eq!(tokens.len(), count);
+    assert_eq!(tokens.last().unwrap().span.end, input.len());
+}
+
+#[test]
+fn test_unicode_identifiers_are_opt_in() {
+    let scanner = Scanner::new("café").scan_tokens();
+    let lexemes: Vec<&str> = scanner
+        .tokens
+        .iter()
+        .map(|token| token.lexeme.as_str())
+        .collect();
+    assert_eq!(lexemes, vec!["caf"]);
+    assert_eq!(
+        scanner.diagnostics.first().unwrap().message,
+        "unexpected character 'é'"
+    );
+}
+
+#[rstest]
+#[case("x\u{2160}", &["x\u{2160}"])]
+#[case("a\u{0301}b", &["\u{e1}b"])]
+#[case("\u{2E2F}", &[])]
+#[case("a\u{00B7}b", &["a\u{00B7}b"])]
+fn test_xid_identifiers(#[case] input: &str, #[case] expected: &[&str]) {
+    let config = LexerConfig::new().unicode_identifiers(true);
+    let tokens = Scanner::with_config(input, config).scan_tokens().tokens;
+    debug!("Tokens: {:?}", tokens);
+    let lexemes: Vec<&str> = tokens.iter().map(|token| token.lexeme.as_str()).collect();
+    assert_eq!(lexemes, expected);
+}
+
+#[test]
+fn test_identifiers_are_normalized() {
+    let code = "var cafe\u{301} = 1; print caf\u{e9};";
+    let config = LexerConfig::new().unicode_identifiers(true).lossless(true);
+    let scanner = Scanner::with_config(code, config).scan_tokens();
+    let names: Vec<&SpannedToken> = scanner
+        .tokens
+        .iter()
+        .filter(|token| token.kind == TokenType::Identifier)
+        .collect();
+    assert_eq!(names.len(), 2);
+    assert_eq!(names[0].lexeme, names[1].lexeme);
+    assert_eq!(names[0].raw_lexeme.as_deref(), Some("cafe\u{301}"));
+    assert_eq!(names[1].raw_lexeme, None);
+
+    // Only the decomposed spelling is reported
+    assert_eq!(scanner.diagnostics.len(), 1);
+    let diagnostic = scanner.diagnostics.first().unwrap();
+    assert_eq!(diagnostic.severity, Severity::Warning);
+    assert_eq!(diagnostic.span, names[0].span);
+    assert_eq!(
+        diagnostic.help,
+        vec!["it is written as `cafe\\u{301}` and read as `caf\\u{e9}`"]
+    );
+
+    // The lossless token stream still reproduces the original spelling
+    let text: String = scanner.tokens.iter().map(SpannedToken::full_text).collect();
+    assert_eq!(text, code);
+}
+
+#[rstest]
+#[case("var p\u{430}ss = 1;", "identifier `p\u{430}ss` mixes scripts")]
+#[case("\u{430}nd", "identifier `\u{430}nd` looks like the keyword `and`")]
+#[case(
+    "var \u{3bf} = 1; var o = 2;",
+    "identifier `o` looks like `\u{3bf}` from line 1, column 5"
+)]
+#[case(
+    "var scope; var sc\u{43e}pe;",
+    "identifier `sc\u{43e}pe` looks like `scope` from line 1, column 5"
+)]
+fn test_confusable_identifiers(#[case] input: &str, #[case] message: &str) {
+    let config = LexerConfig::new().unicode_identifiers(true);
+    let diagnostics = Scanner::with_config(input, config)
+        .scan_tokens()
+        .diagnostics;
+    debug!("Diagnostics: {:?}", diagnostics);
+    let diagnostic = diagnostics
+        .iter()
+        .find(|diagnostic| diagnostic.message == message)
+        .unwrap();
+    assert_eq!(diagnostic.severity, Severity::Warning);
+}
+
+#[rstest]
+#[case("var l = 1; var I = 2; var rn = m;")]
+#[case("var \u{3b1}\u{3b2} = 1; var \u{3b1}\u{3b2} = 2;")]
+#[case("var caf\u{e9} = 1; print caf\u{e9};")]
+fn test_no_confusable_warnings(#[case] input: &str) {
+    let config = LexerConfig::new().unicode_identifiers(true);
+    let diagnostics = Scanner::with_config(input, config)
+        .scan_tokens()
+        .diagnostics;
+    assert_eq!(diagnostics, vec![]);
+}
+
+#[rstest]
+#[case(
+    "var access = \"user\u{202E} \u{2066}// admin\u{2069} \u{2066}\";",
+    "invisible character U+202E (RIGHT-TO-LEFT OVERRIDE) in string literal",
+    18,
+    19
+)]
+#[case(
+    "// check\u{2067}\nvar x;",
+    "invisible character U+2067 (RIGHT-TO-LEFT ISOLATE) in comment",
+    8,
+    9
+)]
+#[case(
+    "/* a\n b\u{200B} */",
+    "invisible character U+200B (ZERO WIDTH SPACE) in comment",
+    7,
+    3
+)]
+#[case(
+    "var is\u{200D}admin;",
+    "invisible character U+200D (ZERO WIDTH JOINER) in identifier",
+    6,
+    7
+)]
+#[case(
+    "\"a\u{2060}b\"",
+    "invisible character U+2060 (WORD JOINER) in string literal",
+    2,
+    3
+)]
+#[case(
+    "// x\u{FEFF}y\nvar z;",
+    "invisible character U+FEFF (ZERO WIDTH NO-BREAK SPACE) in comment",
+    4,
+    5
+)]
+#[case(
+    "/* \u{180E} */",
+    "invisible character U+180E (MONGOLIAN VOWEL SEPARATOR) in comment",
+    3,
+    4
+)]
+fn test_invisible_characters(
+    #[case] input: &str,
+    #[case] message: &str,
+    #[case] start: usize,
+    #[case] column: usize,
+) {
+    for lossless in [false, true] {
+        let config = LexerConfig::new()
+            .unicode_identifiers(true)
+            .lossless(lossless);
+        let scanner = Scanner::with_config(input, config).scan_tokens();
+        debug!("Diagnostics: {:?}", scanner.diagnostics);
+        let diagnostic = scanner.diagnostics.first().unwrap();
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(diagnostic.message, message);
+        assert_eq!(diagnostic.span.start, start);
+        assert_eq!(diagnostic.span.len(), 3);
+        assert_eq!(diagnostic.span.start_column, column);
+        assert_eq!(
+            &input[diagnostic.span.start..diagnostic.span.end]
+                .chars()
+                .count(),
+            &1
+        );
+    }
+}
+
+#[test]
+fn test_every_invisible_character_is_reported() {
+    let input = "\"\u{202E}\u{2066}\u{2069}\" // \u{200F}\u{2069}";
+    let scanner = Scanner::new(input).scan_tokens();
+    assert_eq!(scanner.tokens.len(), 1);
+    assert_eq!(scanner.diagnostics.len(), 5);
+    assert!(scanner.diagnostics.iter().all(|d| d.is_error()));
+}
+
+#[test]
+fn test_token_refs_borrow_from_source() {
+    let code = "var x = (1 + 2.5) * \"s\";";
+    let mut scanner = Scanner::new(code);
+    let refs: Vec<TokenRef> = scanner.token_refs().map(Result::unwrap).collect();
+    assert_eq!(refs.len(), 11);
+    for token in &refs {
+        assert!(matches!(token.lexeme, Cow::Borrowed(_)));
+        assert_eq!(token.lexeme, &code[token.span.start..token.span.end]);
+    }
+    assert_eq!(refs[4].number, Some(Number::Integer(1)));
+
+    // Converting gives the same tokens as the owned iterator
+    let owned = Scanner::new(code).scan_tokens().tokens;
+    for (token, spanned) in refs.into_iter().zip(owned.iter()) {
+        let token = token.into_token();
+        assert_eq!(token.kind, spanned.kind);
+        assert_eq!(token.lexeme, spanned.lexeme);
+        assert_eq!(token.literal, spanned.literal);
+        assert_eq!(token.line, spanned.line);
+    }
+}
+
+#[test]
+fn test_token_refs_yield_errors() {
+    let mut scanner = Scanner::new("a # b");
+    let results: Vec<_> = scanner.token_refs().collect();
+    assert_eq!(results.len(), 3);
+    assert!(matches!(
+        results[1],
+        Err(LexError::UnexpectedCharacter { character: '#', .. })
+    ));
+    assert_eq!(scanner.diagnostics.len(), 1);
+}
+
+#[test]
+fn test_normalized_token_ref_owns_its_lexeme() {
+    let config = LexerConfig::new().unicode_identifiers(true);
+    let mut scanner = Scanner::with_config("cafe\u{301}", config);
+    let token = scanner.next_ref().unwrap().unwrap();
+    assert!(matches!(token.lexeme, Cow::Owned(_)));
+    assert_eq!(token.lexeme, "caf\u{e9}");
+    assert_eq!(token.span.len(), "cafe\u{301}".len());
+}
+
 #[rstest]
 #[case("!*+-/=<> <= ==", 10)]
 #[case("\t >= ", 1)]
@@ -125,7 +1200,7 @@ fn test_scan_tokens(#[case] input: &str, #[case] expected_len: usize) {
     string.push('\n');
 
     let scanner = Scanner::new(&string);
-    let tokens: Vec<Token<LiteralType>> = scanner.scan_tokens().tokens;
+    let tokens: Vec<SpannedToken> = scanner.scan_tokens().tokens;
 
     debug!("Tokens: {:?}", tokens);
     assert_eq!(tokens.len(), expected_len);